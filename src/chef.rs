@@ -1,6 +1,19 @@
+pub mod acl;
+pub mod client;
+pub mod container;
+pub mod cookbook;
 pub mod databag;
 pub mod environment;
+pub mod expand;
+pub mod graph;
+pub mod group;
+pub mod key;
 pub mod node;
+pub mod organization;
+pub mod policy;
 pub mod role;
+pub mod run_list;
 pub mod search;
 pub mod ssh;
+pub mod tag;
+pub mod user;
@@ -0,0 +1,333 @@
+use crate::{client, config::KnifeConfig, prompt::confirm};
+use colored::Colorize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Object types that carry an ACL
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum AclObjectType {
+    Node,
+    Role,
+    Environment,
+    DataBag,
+    Client,
+    Cookbook,
+    Container,
+    Group,
+}
+
+impl AclObjectType {
+    /// endpoint - organization endpoint the objects of this type live under
+    fn endpoint(&self) -> &'static str {
+        match self {
+            Self::Node => "nodes",
+            Self::Role => "roles",
+            Self::Environment => "environments",
+            Self::DataBag => "data",
+            Self::Client => "clients",
+            Self::Cookbook => "cookbooks",
+            Self::Container => "containers",
+            Self::Group => "groups",
+        }
+    }
+}
+
+/// ACL permissions, `all` applies a change to every permission
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AclPermission {
+    Create,
+    Read,
+    Update,
+    Delete,
+    Grant,
+    All,
+}
+
+impl AclPermission {
+    fn names(&self) -> Vec<&'static str> {
+        match self {
+            Self::Create => vec!["create"],
+            Self::Read => vec!["read"],
+            Self::Update => vec!["update"],
+            Self::Delete => vec!["delete"],
+            Self::Grant => vec!["grant"],
+            Self::All => PERMISSIONS.to_vec(),
+        }
+    }
+}
+
+const PERMISSIONS: [&str; 5] = ["create", "read", "update", "delete", "grant"];
+
+/// Kind of ACE member
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum AclMemberType {
+    Actor,
+    Group,
+}
+
+/// AclChange - adding or removing a member from ACEs
+#[derive(Clone, Copy, Debug)]
+pub enum AclChange {
+    Add,
+    Remove,
+}
+
+/// Access control entry of a single permission. Newer Chef Servers split `actors` into `users`
+/// and `clients`, those are folded back into `actors`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Ace {
+    #[serde(default)]
+    pub actors: Vec<String>,
+
+    #[serde(default)]
+    pub groups: Vec<String>,
+
+    #[serde(default, skip_serializing)]
+    pub users: Vec<String>,
+
+    #[serde(default, skip_serializing)]
+    pub clients: Vec<String>,
+}
+
+impl Ace {
+    fn normalize(mut self) -> Self {
+        for actor in self.users.drain(..).chain(self.clients.drain(..)) {
+            if !self.actors.contains(&actor) {
+                self.actors.push(actor);
+            }
+        }
+        self.actors.sort();
+        self.groups.sort();
+        self
+    }
+
+    /// apply - applies a change, returning false when the ACE already was in that state
+    fn apply(&mut self, change: AclChange, member_type: AclMemberType, member: &str) -> bool {
+        let list = match member_type {
+            AclMemberType::Actor => &mut self.actors,
+            AclMemberType::Group => &mut self.groups,
+        };
+        let present = list.iter().any(|m| m == member);
+
+        match change {
+            AclChange::Add if !present => {
+                list.push(member.to_string());
+                true
+            }
+            AclChange::Remove if present => {
+                list.retain(|m| m != member);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn acl_path(config: &KnifeConfig, object_type: AclObjectType, name: &str) -> String {
+    format!(
+        "/organizations/{}/{}/{}/_acl",
+        config.organization,
+        object_type.endpoint(),
+        name
+    )
+}
+
+/// get_acl - Fetches the ACL of an object keyed by permission
+pub async fn get_acl(
+    config: &KnifeConfig,
+    object_type: AclObjectType,
+    name: &str,
+) -> Result<BTreeMap<String, Ace>, Box<dyn Error + Send + Sync>> {
+    let request_path = acl_path(config, object_type, name);
+
+    let r = client::request::get(config, &request_path, "").await?;
+    match r.status {
+        200 => {
+            let acl: BTreeMap<String, Ace> = serde_json::from_str(&r.body)?;
+            Ok(acl
+                .into_iter()
+                .map(|(p, ace)| (p, ace.normalize()))
+                .collect())
+        }
+        403 => Err(format!("Permission denied reading the ACL of {}", name).into()),
+        404 => Err(format!("Object not found: {}", name).into()),
+        _ => Err(format!("acl {}: HTTP Status: {}", name, r.status).into()),
+    }
+}
+
+async fn put_ace(
+    config: &KnifeConfig,
+    object_type: AclObjectType,
+    name: &str,
+    permission: &str,
+    ace: &Ace,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let request_path = format!("{}/{}", acl_path(config, object_type, name), permission);
+    let body = serde_json::to_string(&json!({ permission: ace }))?;
+
+    let r = client::request::put(config, &request_path, &body).await?;
+    match r.status {
+        200 => Ok(()),
+        403 => Err(format!("Permission denied changing the ACL of {}", name).into()),
+        _ => Err(format!("acl {}: HTTP Status: {}: {}", name, r.status, r.body).into()),
+    }
+}
+
+/// show - Prints the actors and groups of every permission of an object
+pub async fn show(
+    config: &KnifeConfig,
+    object_type: AclObjectType,
+    name: &str,
+) -> Result<(), Box<dyn Error>> {
+    let acl = match get_acl(config, object_type, name).await {
+        Ok(a) => a,
+        Err(e) => return Err(format!("acl show: {e}").into()),
+    };
+
+    for permission in PERMISSIONS {
+        let ace = acl.get(permission).cloned().unwrap_or_default();
+        println!("{}:", permission.green().bold());
+        println!("  actors: {}", ace.actors.join(", "));
+        println!("  groups: {}", ace.groups.join(", "));
+    }
+
+    Ok(())
+}
+
+/// change_acl - Adds or removes a member from the permissions of one object, only the ACEs that
+/// actually change are saved.
+async fn change_acl(
+    config: &KnifeConfig,
+    object_type: AclObjectType,
+    name: &str,
+    change: AclChange,
+    permission: AclPermission,
+    member_type: AclMemberType,
+    member: &str,
+) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let acl = get_acl(config, object_type, name).await?;
+
+    let mut changed = 0;
+    for perm in permission.names() {
+        let mut ace = acl.get(perm).cloned().unwrap_or_default();
+        if ace.apply(change, member_type, member) {
+            put_ace(config, object_type, name, perm, &ace).await?;
+            changed += 1;
+        }
+    }
+
+    Ok(changed)
+}
+
+/// edit - Adds or removes an actor or group from a permission of an object
+pub async fn edit(
+    config: &KnifeConfig,
+    object_type: AclObjectType,
+    name: &str,
+    change: AclChange,
+    permission: AclPermission,
+    member_type: AclMemberType,
+    member: &str,
+) -> Result<(), Box<dyn Error>> {
+    match change_acl(
+        config,
+        object_type,
+        name,
+        change,
+        permission,
+        member_type,
+        member,
+    )
+    .await
+    {
+        Ok(0) => {
+            println!("ACL of {} unchanged.", name);
+            Ok(())
+        }
+        Ok(n) => {
+            println!("Updated {} permissions of {}", n, name);
+            Ok(())
+        }
+        Err(e) => Err(format!("acl: {e}").into()),
+    }
+}
+
+/// bulk_edit - Applies the same ACL change to every object of a type whose name matches
+/// `pattern`, after listing the matched objects for confirmation.
+#[allow(clippy::too_many_arguments)]
+pub async fn bulk_edit(
+    config: &KnifeConfig,
+    object_type: AclObjectType,
+    pattern: &str,
+    change: AclChange,
+    permission: AclPermission,
+    member_type: AclMemberType,
+    member: &str,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    let re = Regex::new(pattern)?;
+
+    let request_path = format!(
+        "/organizations/{}/{}",
+        config.organization,
+        object_type.endpoint()
+    );
+    let objects: Value = match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => serde_json::from_str(&r.body)?,
+            _ => return Err(format!("acl bulk: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => return Err(format!("acl bulk: {e}").into()),
+    };
+
+    let names: Vec<&String> = objects
+        .as_object()
+        .map(|m| m.keys().filter(|k| re.is_match(k)).collect())
+        .unwrap_or_default();
+
+    if names.is_empty() {
+        println!("No {} match {}", object_type.endpoint(), pattern);
+        return Ok(());
+    }
+
+    println!("{}:", "Matched objects".green().bold());
+    for name in &names {
+        println!("  {}", name);
+    }
+
+    if !yes && !confirm(&format!("Change the ACL of {} objects?", names.len())) {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for name in &names {
+        match change_acl(
+            config,
+            object_type,
+            name,
+            change,
+            permission,
+            member_type,
+            member,
+        )
+        .await
+        {
+            Ok(0) => println!("{}: unchanged", name),
+            Ok(n) => println!("{}: updated {} permissions", name, n),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("acl bulk: {} objects failed", failed).into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,281 @@
+use crate::{config::KnifeConfig, parse::traverse_json, prompt::confirm};
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+/// RSA key size used for locally generated keys
+const KEY_BITS: u32 = 2048;
+
+/// generate_key_pair - Generates an RSA key pair locally, returning the private and public PEM
+pub fn generate_key_pair() -> Result<(String, String), Box<dyn Error>> {
+    let rsa = Rsa::generate(KEY_BITS)?;
+    let key = PKey::from_rsa(rsa)?;
+
+    let private_pem = String::from_utf8(key.private_key_to_pem_pkcs8()?)?;
+    let public_pem = String::from_utf8(key.public_key_to_pem()?)?;
+
+    Ok((private_pem, public_pem))
+}
+
+/// KeyFile - A private key file opened before the request that returns the key, so a key the
+/// server can't hand out again is never lost to a path that can't be written.
+pub struct KeyFile {
+    file: File,
+    path: String,
+    created: bool,
+}
+
+impl KeyFile {
+    /// open - Opens a key file for writing, readable by the owner only. `mode` only applies when
+    /// the file is created, so the permissions of an existing file are set as well.
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let (file, created) = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)
+        {
+            Ok(file) => (file, true),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                (OpenOptions::new().write(true).open(path)?, false)
+            }
+            Err(e) => return Err(e.into()),
+        };
+        file.set_permissions(Permissions::from_mode(0o600))?;
+        Ok(KeyFile {
+            file,
+            path: path.to_string(),
+            created,
+        })
+    }
+
+    /// discard - Removes the key file when the request failed, as long as `open` created it.
+    /// An empty file left behind could later be taken for a key.
+    pub fn discard(self) {
+        if self.created {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    /// write - Replaces the content of the key file with `private_key`
+    pub fn write(mut self, private_key: &str) -> Result<(), Box<dyn Error>> {
+        self.file.set_len(0)?;
+        self.file.write_all(private_key.as_bytes())?;
+        println!("Private key written to {}", self.path);
+        Ok(())
+    }
+}
+
+/// open_key_out - Opens `--key-out` when given, before the key is requested
+fn open_key_out(key_out: Option<&str>) -> Result<Option<KeyFile>, Box<dyn Error>> {
+    match key_out {
+        Some(path) => match KeyFile::open(path) {
+            Ok(f) => Ok(Some(f)),
+            Err(e) => Err(format!("{}: {}", path, e).into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// save_private_key - Writes a private key to the opened key file, or prints it when no file
+/// was given.
+pub fn save_private_key(
+    private_key: &str,
+    key_file: Option<KeyFile>,
+) -> Result<(), Box<dyn Error>> {
+    match key_file {
+        Some(f) => f.write(private_key),
+        None => {
+            print!("{}", private_key);
+            Ok(())
+        }
+    }
+}
+
+/// list - Lists API clients
+pub async fn list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/clients", config.organization);
+
+    match crate::client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let clients: Value = serde_json::from_str(&r.body)?;
+                for (k, _) in clients.as_object().unwrap() {
+                    println!("{k}");
+                }
+                Ok(())
+            }
+            _ => Err(format!("client list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("client list: {e}").into()),
+    }
+}
+
+/// show - Shows an API client
+pub async fn show(config: &KnifeConfig, client: &str) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/clients/{}", config.organization, client);
+
+    match crate::client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let chef_client: Value = serde_json::from_str(&r.body)?;
+                traverse_json(&chef_client, "");
+                Ok(())
+            }
+            404 => Err(format!("Client not found: {}", client).into()),
+            _ => Err(format!("client show: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("client show: {e}").into()),
+    }
+}
+
+/// create - Creates an API client. The key pair is generated by Chef Server unless `local_key`
+/// is set, in which case only the public half of a locally generated key is sent.
+pub async fn create(
+    config: &KnifeConfig,
+    client: &str,
+    validator: bool,
+    local_key: bool,
+    key_out: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let (body, local_private_key) = if local_key {
+        let (private_pem, public_pem) = generate_key_pair()?;
+        let body = json!({
+            "name": client,
+            "validator": validator,
+            "public_key": public_pem,
+        });
+        (body, Some(private_pem))
+    } else {
+        let body = json!({
+            "name": client,
+            "validator": validator,
+            "create_key": true,
+        });
+        (body, None)
+    };
+
+    // The server generated key can't be fetched again, so make sure it can be saved first
+    let key_file = match open_key_out(key_out) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("client create: {e}").into()),
+    };
+
+    let request_path = format!("/organizations/{}/clients", config.organization);
+    let body = serde_json::to_string(&body)?;
+
+    let response: Result<Value, Box<dyn Error>> =
+        match crate::client::request::post_body(config, &request_path, &body).await {
+            Ok(r) => match r.status {
+                201 => serde_json::from_str(&r.body).map_err(|e| e.into()),
+                409 => Err(format!("Client already exists: {}", client).into()),
+                _ => Err(format!("client create: HTTP Status: {}: {}", r.status, r.body).into()),
+            },
+            Err(e) => Err(format!("client create: {e}").into()),
+        };
+    let response = match response {
+        Ok(v) => v,
+        Err(e) => {
+            if let Some(f) = key_file {
+                f.discard();
+            }
+            return Err(e);
+        }
+    };
+
+    println!("Created client[{}]", client);
+
+    // API v1 nests the generated key under `chef_key`, v0 returns it at the top level
+    let private_key = match local_private_key {
+        Some(k) => k,
+        None => match response["chef_key"]["private_key"]
+            .as_str()
+            .or(response["private_key"].as_str())
+        {
+            Some(k) => k.to_string(),
+            None => return Err("client create: server did not return a private key".into()),
+        },
+    };
+
+    save_private_key(&private_key, key_file)
+}
+
+/// delete - Deletes an API client after confirmation
+pub async fn delete(config: &KnifeConfig, client: &str, yes: bool) -> Result<(), Box<dyn Error>> {
+    if !yes && !confirm(&format!("Do you really want to delete client {}?", client)) {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    match delete_client(config, client).await {
+        Ok(()) => {
+            println!("Deleted client[{}]", client);
+            Ok(())
+        }
+        Err(e) => Err(format!("client delete: {e}").into()),
+    }
+}
+
+/// delete_client - Deletes an API client without asking
+pub async fn delete_client(
+    config: &KnifeConfig,
+    client: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/clients/{}", config.organization, client);
+
+    let r = crate::client::request::delete(config, &request_path).await?;
+    match r.status {
+        200 => Ok(()),
+        404 => Err(format!("Client not found: {}", client).into()),
+        _ => Err(format!("client {}: HTTP Status: {}", client, r.status).into()),
+    }
+}
+
+/// reregister - Replaces the key of an API client with a new server generated key
+pub async fn reregister(
+    config: &KnifeConfig,
+    client: &str,
+    key_out: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    // The old key stops working once the request succeeds, so make sure the new one can be saved
+    let key_file = match open_key_out(key_out) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("client reregister: {e}").into()),
+    };
+
+    let request_path = format!("/organizations/{}/clients/{}", config.organization, client);
+    let body = serde_json::to_string(&json!({
+        "name": client,
+        "private_key": true,
+    }))?;
+
+    let response: Result<Value, Box<dyn Error>> =
+        match crate::client::request::put(config, &request_path, &body).await {
+            Ok(r) => match r.status {
+                200 => serde_json::from_str(&r.body).map_err(|e| e.into()),
+                404 => Err(format!("Client not found: {}", client).into()),
+                _ => {
+                    Err(format!("client reregister: HTTP Status: {}: {}", r.status, r.body).into())
+                }
+            },
+            Err(e) => Err(format!("client reregister: {e}").into()),
+        };
+    let response = match response {
+        Ok(v) => v,
+        Err(e) => {
+            if let Some(f) = key_file {
+                f.discard();
+            }
+            return Err(e);
+        }
+    };
+
+    match response["private_key"].as_str() {
+        Some(k) => save_private_key(k, key_file),
+        None => Err("client reregister: server did not return a private key".into()),
+    }
+}
@@ -0,0 +1,43 @@
+use crate::{client, config::KnifeConfig, parse::traverse_json};
+use serde_json::Value;
+use std::error::Error;
+
+/// list - Lists the containers of the organization
+pub async fn list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/containers", config.organization);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let containers: Value = serde_json::from_str(&r.body)?;
+                for (k, _) in containers.as_object().unwrap() {
+                    println!("{k}");
+                }
+                Ok(())
+            }
+            _ => Err(format!("container list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("container list: {e}").into()),
+    }
+}
+
+/// show - Shows a container
+pub async fn show(config: &KnifeConfig, container: &str) -> Result<(), Box<dyn Error>> {
+    let request_path = format!(
+        "/organizations/{}/containers/{}",
+        config.organization, container
+    );
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let chef_container: Value = serde_json::from_str(&r.body)?;
+                traverse_json(&chef_container, "");
+                Ok(())
+            }
+            404 => Err(format!("Container not found: {}", container).into()),
+            _ => Err(format!("container show: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("container show: {e}").into()),
+    }
+}
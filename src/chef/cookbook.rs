@@ -0,0 +1,1403 @@
+use crate::chef::environment::{self, ChefEnvironment};
+use crate::chef::search::partial_search;
+use crate::{client, config::KnifeConfig, prompt::confirm, ruby};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use colored::Colorize;
+use openssl::error::ErrorStack;
+use openssl::hash::{hash, MessageDigest};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Cookbook manifest segments, in the order knife displays them
+pub const SEGMENTS: [&str; 9] = [
+    "recipes",
+    "attributes",
+    "definitions",
+    "files",
+    "libraries",
+    "providers",
+    "resources",
+    "templates",
+    "root_files",
+];
+
+/// A single file entry of a cookbook version manifest
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CookbookFile {
+    pub name: String,
+    pub path: String,
+    pub checksum: String,
+
+    #[serde(default)]
+    pub specificity: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Cookbook `metadata` as stored in the version manifest. Keys souschef does not use are kept in
+/// `other` so the metadata can be sent back unchanged.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CookbookMetadata {
+    #[serde(default)]
+    pub name: String,
+
+    #[serde(default)]
+    pub version: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub maintainer: String,
+
+    #[serde(default)]
+    pub maintainer_email: String,
+
+    #[serde(default)]
+    pub license: String,
+
+    #[serde(default)]
+    pub platforms: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub recipes: BTreeMap<String, String>,
+
+    #[serde(flatten)]
+    pub other: BTreeMap<String, Value>,
+}
+
+impl CookbookMetadata {
+    /// from_dsl - Reads a simple `metadata.rb` made of literal method calls
+    pub fn from_dsl(source: &str) -> Result<Self, Box<dyn Error>> {
+        let mut metadata = CookbookMetadata::default();
+
+        for call in ruby::parse_dsl(source)? {
+            let line = call.line;
+            let strings: Vec<String> = call
+                .args
+                .iter()
+                .map(|a| match a {
+                    Value::String(s) => Ok(s.clone()),
+                    _ => Err(format!("line {}: {} expects strings", line, call.method)),
+                })
+                .collect::<Result<_, _>>()?;
+            let first = || {
+                strings.first().cloned().ok_or(format!(
+                    "line {}: {} expects an argument",
+                    line, call.method
+                ))
+            };
+            let constraint = strings.get(1).cloned().unwrap_or(">= 0.0.0".to_string());
+
+            match call.method.as_str() {
+                "name" => metadata.name = first()?,
+                "version" => metadata.version = first()?,
+                "description" => metadata.description = first()?,
+                "maintainer" => metadata.maintainer = first()?,
+                "maintainer_email" => metadata.maintainer_email = first()?,
+                "license" => metadata.license = first()?,
+                "depends" => {
+                    metadata.dependencies.insert(first()?, constraint);
+                }
+                "supports" => {
+                    metadata.platforms.insert(first()?, constraint);
+                }
+                "recipe" => {
+                    let description = strings.get(1).cloned().unwrap_or_default();
+                    metadata.recipes.insert(first()?, description);
+                }
+                "chef_version" | "ohai_version" => {
+                    let key = format!("{}s", call.method);
+                    let entry = metadata
+                        .other
+                        .entry(key)
+                        .or_insert(Value::Array(Vec::new()));
+                    if let Value::Array(list) = entry {
+                        list.push(Value::from(strings));
+                    }
+                }
+                "long_description" | "source_url" | "issues_url" | "privacy" => {
+                    metadata
+                        .other
+                        .insert(call.method.clone(), Value::String(first()?));
+                }
+                other => {
+                    return Err(format!(
+                        "line {}: unsupported metadata.rb method '{}'",
+                        line, other
+                    )
+                    .into())
+                }
+            }
+        }
+
+        if metadata.name.is_empty() {
+            return Err("metadata.rb does not set a name".into());
+        }
+        if metadata.version.is_empty() {
+            metadata.version = "0.0.0".to_string();
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Cookbook version manifest from `/cookbooks/NAME/VERSION`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CookbookVersion {
+    pub cookbook_name: String,
+    pub version: String,
+    pub name: String,
+
+    #[serde(default)]
+    pub metadata: CookbookMetadata,
+
+    #[serde(default, rename = "frozen?")]
+    pub frozen: bool,
+
+    #[serde(default)]
+    pub recipes: Vec<CookbookFile>,
+
+    #[serde(default)]
+    pub attributes: Vec<CookbookFile>,
+
+    #[serde(default)]
+    pub definitions: Vec<CookbookFile>,
+
+    #[serde(default)]
+    pub files: Vec<CookbookFile>,
+
+    #[serde(default)]
+    pub libraries: Vec<CookbookFile>,
+
+    #[serde(default)]
+    pub providers: Vec<CookbookFile>,
+
+    #[serde(default)]
+    pub resources: Vec<CookbookFile>,
+
+    #[serde(default)]
+    pub templates: Vec<CookbookFile>,
+
+    #[serde(default)]
+    pub root_files: Vec<CookbookFile>,
+
+    /// Segment-less manifest used by newer server API versions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub all_files: Vec<CookbookFile>,
+
+    #[serde(default = "cookbook_json_class")]
+    pub json_class: String,
+
+    #[serde(default = "cookbook_chef_type")]
+    pub chef_type: String,
+}
+
+fn cookbook_json_class() -> String {
+    "Chef::CookbookVersion".to_string()
+}
+
+fn cookbook_chef_type() -> String {
+    "cookbook_version".to_string()
+}
+
+impl CookbookVersion {
+    /// segment - files of a manifest segment
+    pub fn segment(&self, segment: &str) -> Vec<&CookbookFile> {
+        if !self.all_files.is_empty() {
+            // `all_files` names look like `recipes/default.rb`, top level files have no prefix
+            return self
+                .all_files
+                .iter()
+                .filter(|f| match f.name.split_once('/') {
+                    Some((prefix, _)) if SEGMENTS.contains(&prefix) => prefix == segment,
+                    _ => segment == "root_files",
+                })
+                .collect();
+        }
+
+        let files = match segment {
+            "recipes" => &self.recipes,
+            "attributes" => &self.attributes,
+            "definitions" => &self.definitions,
+            "files" => &self.files,
+            "libraries" => &self.libraries,
+            "providers" => &self.providers,
+            "resources" => &self.resources,
+            "templates" => &self.templates,
+            "root_files" => &self.root_files,
+            _ => return Vec::new(),
+        };
+        files.iter().collect()
+    }
+}
+
+/// compare_versions - compares `x.y.z` cookbook versions numerically
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |v: &str| -> Vec<u64> { v.split('.').map(|p| p.parse().unwrap_or(0)).collect() };
+    let (mut a, mut b) = (parse(a), parse(b));
+
+    // `1.2` and `1.2.0` are the same version
+    let len = a.len().max(b.len());
+    a.resize(len, 0);
+    b.resize(len, 0);
+    a.cmp(&b)
+}
+
+/// version_satisfies - checks a version against a Chef constraint such as `= 1.2.0`, `>= 1.0`
+/// or `~> 1.2`. A bare version is treated as `=`.
+pub fn version_satisfies(version: &str, constraint: &str) -> bool {
+    let constraint = constraint.trim();
+    let (op, target) = ["~>", ">=", "<=", "=", ">", "<"]
+        .iter()
+        .find_map(|op| constraint.strip_prefix(op).map(|t| (*op, t.trim())))
+        .unwrap_or(("=", constraint));
+
+    let ord = compare_versions(version, target);
+    match op {
+        "=" => ord == Ordering::Equal,
+        ">" => ord == Ordering::Greater,
+        ">=" => ord != Ordering::Less,
+        "<" => ord == Ordering::Less,
+        "<=" => ord != Ordering::Greater,
+        "~>" => {
+            ord != Ordering::Less
+                && compare_versions(version, &pessimistic_bound(target)) == Ordering::Less
+        }
+        _ => false,
+    }
+}
+
+/// pessimistic_bound - exclusive upper bound of `~> target`: `~> 1.2` is below `2`, `~> 1.2.3`
+/// is below `1.3`
+fn pessimistic_bound(target: &str) -> String {
+    let mut parts: Vec<u64> = target.split('.').map(|p| p.parse().unwrap_or(0)).collect();
+    if parts.len() > 1 {
+        parts.pop();
+    }
+    if let Some(last) = parts.last_mut() {
+        *last += 1;
+    }
+    parts
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// list - Lists cookbooks with their latest version, or every version with `all_versions`
+pub async fn list(config: &KnifeConfig, all_versions: bool) -> Result<(), Box<dyn Error>> {
+    if all_versions {
+        let versions = match list_versions(config).await {
+            Ok(v) => v,
+            Err(e) => return Err(format!("cookbook list: {e}").into()),
+        };
+
+        let width = versions.keys().map(|k| k.len()).max().unwrap_or(0);
+        for (name, versions) in versions {
+            println!("{:<width$}  {}", name, versions.join("  "));
+        }
+        return Ok(());
+    }
+
+    let request_path = format!("/organizations/{}/cookbooks/_latest", config.organization);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let latest: BTreeMap<String, String> = serde_json::from_str(&r.body)?;
+                let width = latest.keys().map(|k| k.len()).max().unwrap_or(0);
+
+                // The value is the URL of the latest version, which ends with the version
+                for (name, url) in latest {
+                    let version = url.rsplit('/').next().unwrap_or_default();
+                    println!("{:<width$}  {}", name, version);
+                }
+                Ok(())
+            }
+            _ => Err(format!("cookbook list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("cookbook list: {e}").into()),
+    }
+}
+
+/// list_versions - Fetches every cookbook with all of its versions, newest first
+pub async fn list_versions(
+    config: &KnifeConfig,
+) -> Result<BTreeMap<String, Vec<String>>, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/cookbooks", config.organization);
+
+    let r = client::request::get_params(config, &request_path, &[("num_versions", "all")]).await?;
+    match r.status {
+        200 => {
+            let cookbooks: Value = serde_json::from_str(&r.body)?;
+            Ok(parse_versions(&cookbooks))
+        }
+        _ => Err(format!("HTTP Status: {}", r.status).into()),
+    }
+}
+
+/// parse_versions - reads the `{"name": {"versions": [{"version": ...}]}}` cookbook listing
+fn parse_versions(cookbooks: &Value) -> BTreeMap<String, Vec<String>> {
+    let mut result = BTreeMap::new();
+
+    if let Some(map) = cookbooks.as_object() {
+        for (name, cookbook) in map {
+            let mut versions: Vec<String> = cookbook["versions"]
+                .as_array()
+                .map(|v| {
+                    v.iter()
+                        .filter_map(|v| v["version"].as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            versions.sort_by(|a, b| compare_versions(b, a));
+            result.insert(name.clone(), versions);
+        }
+    }
+
+    result
+}
+
+/// get_versions - Fetches every version of a single cookbook, newest first
+pub async fn get_versions(
+    config: &KnifeConfig,
+    cookbook: &str,
+) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let request_path = format!(
+        "/organizations/{}/cookbooks/{}",
+        config.organization, cookbook
+    );
+
+    let r = client::request::get_params(config, &request_path, &[("num_versions", "all")]).await?;
+    match r.status {
+        200 => {
+            let cookbooks: Value = serde_json::from_str(&r.body)?;
+            Ok(parse_versions(&cookbooks)
+                .remove(cookbook)
+                .unwrap_or_default())
+        }
+        404 => Err(format!("Cookbook not found: {}", cookbook).into()),
+        _ => Err(format!("HTTP Status: {}", r.status).into()),
+    }
+}
+
+/// get_cookbook_version - Fetches the manifest of a cookbook version, `_latest` is accepted
+pub async fn get_cookbook_version(
+    config: &KnifeConfig,
+    cookbook: &str,
+    version: &str,
+) -> Result<CookbookVersion, Box<dyn Error + Send + Sync>> {
+    let request_path = format!(
+        "/organizations/{}/cookbooks/{}/{}",
+        config.organization, cookbook, version
+    );
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => match serde_json::from_str(&r.body) {
+                Ok(cb) => Ok(cb),
+                Err(e) => Err(format!("parsing cookbook {} {}: {}", cookbook, version, e).into()),
+            },
+            404 => Err(format!("Cookbook not found: {} {}", cookbook, version).into()),
+            _ => Err(format!("cookbook {}: HTTP Status: {}", cookbook, r.status).into()),
+        },
+        Err(e) => Err(format!("cookbook {}: {}", cookbook, e).into()),
+    }
+}
+
+/// show - Shows the versions of a cookbook, or the files of a cookbook version optionally limited
+/// to a single segment such as `recipes` or `templates`.
+pub async fn show(
+    config: &KnifeConfig,
+    cookbook: &str,
+    version: Option<&str>,
+    part: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let Some(version) = version else {
+        return match get_versions(config, cookbook).await {
+            Ok(versions) => {
+                println!("{}:", cookbook.green().bold());
+                for v in versions {
+                    println!("  {}", v);
+                }
+                Ok(())
+            }
+            Err(e) => Err(format!("cookbook show: {e}").into()),
+        };
+    };
+
+    if let Some(part) = part {
+        if !SEGMENTS.contains(&part) {
+            return Err(format!(
+                "cookbook show: unknown part {}, expected one of: {}",
+                part,
+                SEGMENTS.join(", ")
+            )
+            .into());
+        }
+    }
+
+    let cb = match get_cookbook_version(config, cookbook, version).await {
+        Ok(cb) => cb,
+        Err(e) => return Err(format!("cookbook show: {e}").into()),
+    };
+
+    println!("{}:    {}", "Cookbook".green().bold(), cb.cookbook_name);
+    println!("{}:     {}", "Version".green().bold(), cb.version);
+    println!("{}:      {}", "Frozen".green().bold(), cb.frozen);
+
+    for segment in SEGMENTS {
+        if part.is_some_and(|p| p != segment) {
+            continue;
+        }
+
+        let files = cb.segment(segment);
+        if files.is_empty() {
+            continue;
+        }
+
+        println!("\n{}:", segment.green().bold());
+        let width = files.iter().map(|f| f.path.len()).max().unwrap_or(0);
+        for f in files {
+            println!("  {:<width$}  {}", f.path, f.checksum);
+        }
+    }
+
+    Ok(())
+}
+
+/// metadata - Shows the metadata of a cookbook version with its dependencies and platforms
+pub async fn metadata(
+    config: &KnifeConfig,
+    cookbook: &str,
+    version: &str,
+) -> Result<(), Box<dyn Error>> {
+    let cb = match get_cookbook_version(config, cookbook, version).await {
+        Ok(cb) => cb,
+        Err(e) => return Err(format!("cookbook metadata: {e}").into()),
+    };
+    let m = &cb.metadata;
+
+    println!("{}:         {}", "Name".green().bold(), m.name);
+    println!("{}:      {}", "Version".green().bold(), m.version);
+    println!("{}:  {}", "Description".green().bold(), m.description);
+    println!(
+        "{}:   {} <{}>",
+        "Maintainer".green().bold(),
+        m.maintainer,
+        m.maintainer_email
+    );
+    println!("{}:      {}", "License".green().bold(), m.license);
+
+    println!("\n{}:", "Dependencies".green().bold());
+    for (name, constraint) in &m.dependencies {
+        println!("  {} {}", name, constraint);
+    }
+
+    println!("\n{}:", "Platforms".green().bold());
+    for (name, constraint) in &m.platforms {
+        println!("  {} {}", name, constraint);
+    }
+
+    if !m.recipes.is_empty() {
+        println!("\n{}:", "Recipes".green().bold());
+        for (name, description) in &m.recipes {
+            println!("  {} {}", name, description.dimmed());
+        }
+    }
+
+    Ok(())
+}
+
+/// Number of cookbook files transferred at the same time
+const TRANSFER_CONCURRENCY: usize = 10;
+
+/// md5_hex - cookbook file checksums are hex encoded MD5 digests
+pub fn md5_hex(content: &[u8]) -> Result<String, ErrorStack> {
+    let digest = hash(MessageDigest::md5(), content)?;
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// safe_relative_path - refuses manifest paths that would escape the cookbook directory
+fn safe_relative_path(path: &str) -> Result<PathBuf, String> {
+    let p = Path::new(path);
+    if p.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(p.to_path_buf())
+    } else {
+        Err(format!("refusing unsafe cookbook file path: {}", path))
+    }
+}
+
+/// download - Downloads a cookbook version into `DIR/NAME-VERSION`, verifying the checksum of
+/// every file against the manifest. With `force` an existing directory is only replaced once
+/// the whole cookbook has been downloaded.
+pub async fn download(
+    config: &KnifeConfig,
+    cookbook: &str,
+    version: Option<&str>,
+    latest: bool,
+    dir: &str,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    let version = match (version, latest) {
+        (Some(v), _) => v.to_string(),
+        (None, true) => "_latest".to_string(),
+        (None, false) => {
+            let versions = match get_versions(config, cookbook).await {
+                Ok(v) => v,
+                Err(e) => return Err(format!("cookbook download: {e}").into()),
+            };
+            match versions.as_slice() {
+                [only] => only.clone(),
+                _ => {
+                    return Err(format!(
+                        "cookbook download: {} has versions {}, pass a VERSION or --latest",
+                        cookbook,
+                        versions.join(", ")
+                    )
+                    .into())
+                }
+            }
+        }
+    };
+
+    let cb = match get_cookbook_version(config, cookbook, &version).await {
+        Ok(cb) => cb,
+        Err(e) => return Err(format!("cookbook download: {e}").into()),
+    };
+
+    let target = Path::new(dir).join(format!("{}-{}", cb.cookbook_name, cb.version));
+    if target.exists() && !force {
+        return Err(format!(
+            "cookbook download: {} already exists, use --force to overwrite",
+            target.display()
+        )
+        .into());
+    }
+
+    // Files are fetched into a sibling staging directory which only replaces the target once
+    // every file has been downloaded and verified.
+    let staging = target.with_file_name(format!(
+        ".{}-{}.download-{}",
+        cb.cookbook_name,
+        cb.version,
+        std::process::id()
+    ));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(&staging)?;
+
+    println!(
+        "Downloading {} {} to {}",
+        cb.cookbook_name,
+        cb.version,
+        target.display()
+    );
+
+    let semaphore = Arc::new(Semaphore::new(TRANSFER_CONCURRENCY));
+    let mut handles = Vec::new();
+
+    for segment in SEGMENTS {
+        for file in cb.segment(segment) {
+            let file = file.clone();
+            let staging = staging.clone();
+            let semaphore = Arc::clone(&semaphore);
+
+            let handle = tokio::task::spawn(async move {
+                let _permit = semaphore.acquire().await;
+                let result = download_file(&file, &staging).await;
+                (file.path, result)
+            });
+            handles.push(handle);
+        }
+    }
+
+    let mut failed = 0;
+    for h in handles {
+        match h.await {
+            Ok((_, Ok(()))) => {}
+            Ok((path, Err(e))) => {
+                eprintln!("{}: {}", path, e);
+                failed += 1;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        fs::remove_dir_all(&staging)?;
+        return Err(format!("cookbook download: {} files failed", failed).into());
+    }
+
+    if target.exists() {
+        fs::remove_dir_all(&target)?;
+    }
+    fs::rename(&staging, &target)?;
+
+    println!("Cookbook downloaded to {}", target.display());
+    Ok(())
+}
+
+async fn download_file(
+    file: &CookbookFile,
+    target: &Path,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let url = match &file.url {
+        Some(u) => u,
+        None => return Err("manifest has no download URL".into()),
+    };
+
+    let content = client::request::get_file(url).await?;
+
+    let checksum = md5_hex(&content)?;
+    if checksum != file.checksum {
+        return Err(format!(
+            "checksum mismatch, expected {} got {}",
+            file.checksum, checksum
+        )
+        .into());
+    }
+
+    let path = target.join(safe_relative_path(&file.path)?);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+
+    Ok(())
+}
+
+/// A local cookbook file staged for upload
+struct LocalFile {
+    segment: &'static str,
+    file: CookbookFile,
+    content: Vec<u8>,
+}
+
+/// read_chefignore - reads `chefignore` patterns, `*` being the only supported wildcard
+fn read_chefignore(cookbook_dir: &Path) -> Vec<String> {
+    fs::read_to_string(cookbook_dir.join("chefignore"))
+        .map(|c| {
+            c.lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// glob_match - matches `path` against a pattern where `*` matches any run of characters
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == path;
+    }
+
+    let mut rest = match path.strip_prefix(parts[0]) {
+        Some(r) => r,
+        None => return false,
+    };
+    for (i, part) in parts.iter().enumerate().skip(1) {
+        if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// collect_files - walks the cookbook directory and returns every file relative to it, skipping
+/// hidden entries and `chefignore` matches.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    ignore: &[String],
+    files: &mut Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(root)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if ignore.iter().any(|p| glob_match(p, &relative)) {
+            continue;
+        }
+
+        if path.is_dir() {
+            collect_files(root, &path, ignore, files)?;
+        } else {
+            files.push(relative);
+        }
+    }
+
+    Ok(())
+}
+
+/// manifest_entry - places a relative path in its manifest segment. Files outside of the known
+/// segment directories are only uploaded when they sit at the top of the cookbook.
+fn manifest_entry(relative: &str, checksum: String) -> Option<(&'static str, CookbookFile)> {
+    let parts: Vec<&str> = relative.split('/').collect();
+
+    let (segment, name, specificity) = match parts.as_slice() {
+        [file] => ("root_files", file.to_string(), "default".to_string()),
+        [dir, rest @ ..] => {
+            let segment = *SEGMENTS.iter().find(|s| *s == dir && **s != "root_files")?;
+            match segment {
+                "files" | "templates" if rest.len() > 1 => {
+                    (segment, rest[1..].join("/"), rest[0].to_string())
+                }
+                _ => (segment, rest.join("/"), "default".to_string()),
+            }
+        }
+        [] => return None,
+    };
+
+    Some((
+        segment,
+        CookbookFile {
+            name,
+            path: relative.to_string(),
+            checksum,
+            specificity,
+            url: None,
+        },
+    ))
+}
+
+/// load_cookbook - reads the cookbook metadata and files from `path`. When only `metadata.rb`
+/// exists a `metadata.json` is generated and uploaded with the cookbook, like knife does.
+fn load_cookbook(path: &Path) -> Result<(CookbookMetadata, Vec<LocalFile>), Box<dyn Error>> {
+    let json_path = path.join("metadata.json");
+    let rb_path = path.join("metadata.rb");
+
+    let (metadata, generated_json) = if json_path.exists() {
+        let content = fs::read_to_string(&json_path)?;
+        let metadata: CookbookMetadata = match serde_json::from_str(&content) {
+            Ok(m) => m,
+            Err(e) => return Err(format!("{}: {}", json_path.display(), e).into()),
+        };
+        (metadata, None)
+    } else if rb_path.exists() {
+        let content = fs::read_to_string(&rb_path)?;
+        let metadata = match CookbookMetadata::from_dsl(&content) {
+            Ok(m) => m,
+            Err(e) => return Err(format!("{}: {}", rb_path.display(), e).into()),
+        };
+        let json = serde_json::to_string_pretty(&metadata)?;
+        (metadata, Some(json))
+    } else {
+        return Err(format!("{}: no metadata.json or metadata.rb found", path.display()).into());
+    };
+
+    let mut relative_paths = Vec::new();
+    collect_files(path, path, &read_chefignore(path), &mut relative_paths)?;
+
+    let mut contents: Vec<(String, Vec<u8>)> = Vec::new();
+    for relative in relative_paths {
+        let content = fs::read(path.join(&relative))?;
+        contents.push((relative, content));
+    }
+    if let Some(json) = generated_json {
+        contents.push(("metadata.json".to_string(), json.into_bytes()));
+    }
+
+    let mut files = Vec::new();
+    for (relative, content) in contents {
+        let checksum = md5_hex(&content)?;
+        if let Some((segment, file)) = manifest_entry(&relative, checksum) {
+            files.push(LocalFile {
+                segment,
+                file,
+                content,
+            });
+        }
+    }
+
+    Ok((metadata, files))
+}
+
+/// upload - Uploads a cookbook directory: the file checksums are sent to a new sandbox, only
+/// the files Chef Server does not have yet are uploaded, and once the sandbox is committed the
+/// cookbook version manifest is saved.
+pub async fn upload(
+    config: &KnifeConfig,
+    path: &str,
+    freeze: bool,
+    force: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (metadata, files) = load_cookbook(Path::new(path))?;
+    let name = metadata.name.clone();
+    let version = metadata.version.clone();
+
+    println!("Uploading {} [{}]", name, version);
+
+    // Create the sandbox
+    let checksums: HashMap<&str, Value> = files
+        .iter()
+        .map(|f| (f.file.checksum.as_str(), Value::Null))
+        .collect();
+    let body = serde_json::to_string(&serde_json::json!({ "checksums": checksums }))?;
+    let request_path = format!("/organizations/{}/sandboxes", config.organization);
+
+    let sandbox: Value = match client::request::post_body(config, &request_path, &body).await {
+        Ok(r) => match r.status {
+            201 => serde_json::from_str(&r.body)?,
+            _ => {
+                return Err(format!(
+                    "cookbook upload: sandbox: HTTP Status: {}: {}",
+                    r.status, r.body
+                )
+                .into())
+            }
+        },
+        Err(e) => return Err(format!("cookbook upload: sandbox: {e}").into()),
+    };
+
+    let sandbox_id = match sandbox["sandbox_id"].as_str() {
+        Some(id) => id.to_string(),
+        None => return Err("cookbook upload: sandbox response has no sandbox_id".into()),
+    };
+
+    // Upload the files the server asked for, each checksum only once
+    let semaphore = Arc::new(Semaphore::new(TRANSFER_CONCURRENCY));
+    let mut handles = Vec::new();
+    let mut queued = Vec::new();
+
+    for f in &files {
+        let entry = &sandbox["checksums"][&f.file.checksum];
+        if entry["needs_upload"].as_bool() != Some(true) || queued.contains(&f.file.checksum) {
+            continue;
+        }
+        let url = match entry["url"].as_str() {
+            Some(u) => u.to_string(),
+            None => continue,
+        };
+        queued.push(f.file.checksum.clone());
+
+        let content = f.content.clone();
+        let content_md5 = BASE64.encode(hash(MessageDigest::md5(), &content)?);
+        let file_path = f.file.path.clone();
+        let semaphore = Arc::clone(&semaphore);
+
+        handles.push(tokio::task::spawn(async move {
+            let _permit = semaphore.acquire().await;
+            let result = client::request::put_file(&url, content, &content_md5).await;
+            (file_path, result)
+        }));
+    }
+
+    let uploaded = handles.len();
+    let mut failed = 0;
+    for h in handles {
+        let (file_path, result) = h.await?;
+        if let Err(e) = result {
+            eprintln!("{}: {}", file_path, e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("cookbook upload: {} files failed to upload", failed).into());
+    }
+    println!(
+        "Uploaded {} of {} files, the server already had the rest",
+        uploaded,
+        files.len()
+    );
+
+    // Commit the sandbox
+    let request_path = format!(
+        "/organizations/{}/sandboxes/{}",
+        config.organization, sandbox_id
+    );
+    match client::request::put(config, &request_path, r#"{"is_completed":true}"#).await {
+        Ok(r) if r.status == 200 => {}
+        Ok(r) => {
+            return Err(format!(
+                "cookbook upload: committing sandbox: HTTP Status: {}: {}",
+                r.status, r.body
+            )
+            .into())
+        }
+        Err(e) => return Err(format!("cookbook upload: committing sandbox: {e}").into()),
+    }
+
+    // Save the cookbook version manifest
+    let mut manifest = CookbookVersion {
+        cookbook_name: name.clone(),
+        version: version.clone(),
+        name: format!("{}-{}", name, version),
+        metadata,
+        frozen: freeze,
+        recipes: Vec::new(),
+        attributes: Vec::new(),
+        definitions: Vec::new(),
+        files: Vec::new(),
+        libraries: Vec::new(),
+        providers: Vec::new(),
+        resources: Vec::new(),
+        templates: Vec::new(),
+        root_files: Vec::new(),
+        all_files: Vec::new(),
+        json_class: cookbook_json_class(),
+        chef_type: cookbook_chef_type(),
+    };
+    for f in files {
+        let segment = match f.segment {
+            "recipes" => &mut manifest.recipes,
+            "attributes" => &mut manifest.attributes,
+            "definitions" => &mut manifest.definitions,
+            "files" => &mut manifest.files,
+            "libraries" => &mut manifest.libraries,
+            "providers" => &mut manifest.providers,
+            "resources" => &mut manifest.resources,
+            "templates" => &mut manifest.templates,
+            _ => &mut manifest.root_files,
+        };
+        segment.push(f.file);
+    }
+
+    let request_path = format!(
+        "/organizations/{}/cookbooks/{}/{}",
+        config.organization, name, version
+    );
+    let body = serde_json::to_string(&manifest)?;
+    let params: &[(&str, &str)] = if force { &[("force", "true")] } else { &[] };
+
+    match client::request::put_params(config, &request_path, &body, params).await {
+        Ok(r) => match r.status {
+            200 | 201 => {
+                println!(
+                    "Uploaded {} [{}]{}",
+                    name,
+                    version,
+                    if freeze { " (frozen)" } else { "" }
+                );
+                Ok(())
+            }
+            409 => Err(format!(
+                "cookbook upload: {} [{}] is frozen, use --force to overwrite it",
+                name, version
+            )
+            .into()),
+            _ => Err(format!("cookbook upload: HTTP Status: {}: {}", r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("cookbook upload: {e}").into()),
+    }
+}
+
+/// Node names keyed by cookbook name and then by the cookbook version they last ran
+type NodeCookbookVersions = HashMap<String, BTreeMap<String, Vec<String>>>;
+
+/// fetch_usage - Fetches every environment and, through a partial search on the `cookbooks`
+/// automatic attribute, the cookbook versions each node ran.
+async fn fetch_usage(
+    config: &KnifeConfig,
+) -> Result<(Vec<ChefEnvironment>, NodeCookbookVersions), Box<dyn Error + Send + Sync>> {
+    let environments = environment::fetch_all(config).await?;
+
+    let keys = json!({
+        "name": ["name"],
+        "cookbooks": ["cookbooks"]
+    });
+    let rows = partial_search(config, "node", "*:*", &keys).await?;
+
+    let mut nodes = NodeCookbookVersions::new();
+    for row in rows {
+        let name = row["name"].as_str().unwrap_or_default().to_string();
+        let Some(cookbooks) = row["cookbooks"].as_object() else {
+            continue;
+        };
+        for (cookbook, info) in cookbooks {
+            if let Some(version) = info["version"].as_str() {
+                nodes
+                    .entry(cookbook.clone())
+                    .or_default()
+                    .entry(version.to_string())
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+    }
+
+    Ok((environments, nodes))
+}
+
+/// resolve_environments - the version each environment resolves `cookbook` to: the newest
+/// version satisfying its constraint, or the newest version without a constraint.
+fn resolve_environments<'a>(
+    cookbook: &str,
+    versions: &'a [String],
+    environments: &[ChefEnvironment],
+) -> BTreeMap<String, (Option<String>, Option<&'a String>)> {
+    environments
+        .iter()
+        .map(|env| {
+            let constraint = env.cookbook_versions.get(cookbook).cloned();
+            let resolved = versions.iter().find(|v| match &constraint {
+                Some(c) => version_satisfies(v, c),
+                None => true,
+            });
+            (env.name.clone(), (constraint, resolved))
+        })
+        .collect()
+}
+
+/// usage - Shows which version each environment allows and which versions nodes ran
+pub async fn usage(config: &KnifeConfig, cookbook: &str) -> Result<(), Box<dyn Error>> {
+    let versions = match get_versions(config, cookbook).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("cookbook usage: {e}").into()),
+    };
+
+    let (environments, nodes) = match fetch_usage(config).await {
+        Ok(u) => u,
+        Err(e) => return Err(format!("cookbook usage: {e}").into()),
+    };
+    let node_versions = nodes.get(cookbook).cloned().unwrap_or_default();
+    let resolved = resolve_environments(cookbook, &versions, &environments);
+
+    println!("{}:", "Environments".green().bold());
+    let width = resolved.keys().map(|k| k.len()).max().unwrap_or(0);
+    for (env, (constraint, version)) in &resolved {
+        let constraint = constraint.as_deref().unwrap_or("(no constraint)");
+        match version {
+            Some(v) => println!("  {:<width$}  {:<16} -> {}", env, constraint, v),
+            None => println!(
+                "  {:<width$}  {:<16} -> {}",
+                env,
+                constraint,
+                "no matching version".red()
+            ),
+        }
+    }
+
+    println!("\n{}:", "Versions".green().bold());
+    for version in &versions {
+        let envs: Vec<&str> = resolved
+            .iter()
+            .filter(|(_, (_, v))| *v == Some(version))
+            .map(|(env, _)| env.as_str())
+            .collect();
+        let node_count = node_versions.get(version).map(|n| n.len()).unwrap_or(0);
+
+        if envs.is_empty() && node_count == 0 {
+            println!("  {:<12} {}", version, "unused".yellow());
+        } else {
+            println!(
+                "  {:<12} {} nodes, environments: {}",
+                version,
+                node_count,
+                if envs.is_empty() {
+                    "-".to_string()
+                } else {
+                    envs.join(", ")
+                }
+            );
+        }
+    }
+
+    // Nodes can run versions that were deleted from the server since
+    for (version, node_names) in &node_versions {
+        if !versions.contains(version) {
+            println!(
+                "  {:<12} {} nodes, {}",
+                version,
+                node_names.len(),
+                "not on the server anymore".red()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Dependency constraints keyed by cookbook name and then by cookbook version
+type CookbookDependencies = HashMap<String, HashMap<String, BTreeMap<String, String>>>;
+
+/// get_dependencies - Fetches the dependencies of every cookbook version from `/universe`
+async fn get_dependencies(
+    config: &KnifeConfig,
+) -> Result<CookbookDependencies, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/universe", config.organization);
+
+    let r = client::request::get(config, &request_path, "").await?;
+    let universe: Value = match r.status {
+        200 => serde_json::from_str(&r.body)?,
+        _ => return Err(format!("universe: HTTP Status: {}", r.status).into()),
+    };
+
+    let mut dependencies = CookbookDependencies::new();
+    for (name, versions) in universe.as_object().into_iter().flatten() {
+        for (version, info) in versions.as_object().into_iter().flatten() {
+            let deps = serde_json::from_value(info["dependencies"].clone()).unwrap_or_default();
+            dependencies
+                .entry(name.clone())
+                .or_default()
+                .insert(version.clone(), deps);
+        }
+    }
+
+    Ok(dependencies)
+}
+
+/// prune_candidates - Selects the cookbook versions to delete. Every version satisfying a
+/// dependency constraint of a protected version is protected as well, following dependencies of
+/// dependencies, so a kept version never loses a cookbook it depends on.
+fn prune_candidates(
+    cookbooks: &BTreeMap<String, Vec<String>>,
+    mut protected: BTreeSet<(String, String)>,
+    dependencies: &CookbookDependencies,
+) -> Vec<(String, String)> {
+    let mut pending: Vec<(String, String)> = protected.iter().cloned().collect();
+
+    while let Some((name, version)) = pending.pop() {
+        let Some(deps) = dependencies.get(&name).and_then(|d| d.get(&version)) else {
+            continue;
+        };
+        for (dep, constraint) in deps {
+            for v in cookbooks.get(dep).into_iter().flatten() {
+                if version_satisfies(v, constraint) && protected.insert((dep.clone(), v.clone())) {
+                    pending.push((dep.clone(), v.clone()));
+                }
+            }
+        }
+    }
+
+    cookbooks
+        .iter()
+        .flat_map(|(name, versions)| versions.iter().map(move |v| (name.clone(), v.clone())))
+        .filter(|c| !protected.contains(c))
+        .collect()
+}
+
+/// prune - Deletes cookbook versions that no environment resolves to, no node ran and no kept
+/// version depends on, always keeping the newest `keep` versions of each cookbook. The versions
+/// are listed before deletion and nothing is deleted with `dry_run`.
+pub async fn prune(
+    config: &KnifeConfig,
+    cookbook: Option<&str>,
+    keep: usize,
+    dry_run: bool,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    // Every cookbook is needed even when pruning one, other cookbooks can depend on it
+    let cookbooks = match list_versions(config).await {
+        Ok(v) => v,
+        Err(e) => return Err(format!("cookbook prune: {e}").into()),
+    };
+    if let Some(name) = cookbook {
+        if !cookbooks.contains_key(name) {
+            return Err(format!("cookbook prune: Cookbook not found: {}", name).into());
+        }
+    }
+
+    let (environments, nodes) = match fetch_usage(config).await {
+        Ok(u) => u,
+        Err(e) => return Err(format!("cookbook prune: {e}").into()),
+    };
+    let dependencies = match get_dependencies(config).await {
+        Ok(d) => d,
+        Err(e) => return Err(format!("cookbook prune: {e}").into()),
+    };
+
+    let mut protected = BTreeSet::new();
+    for (name, versions) in &cookbooks {
+        let mut keep_versions: Vec<&String> = versions.iter().take(keep).collect();
+        for (_, version) in resolve_environments(name, versions, &environments).values() {
+            keep_versions.extend(version);
+        }
+        if let Some(node_versions) = nodes.get(name) {
+            keep_versions.extend(node_versions.keys());
+        }
+        protected.extend(keep_versions.into_iter().map(|v| (name.clone(), v.clone())));
+    }
+
+    let mut candidates = prune_candidates(&cookbooks, protected, &dependencies);
+    if let Some(name) = cookbook {
+        candidates.retain(|(n, _)| n == name);
+    }
+
+    if candidates.is_empty() {
+        println!("Nothing to prune.");
+        return Ok(());
+    }
+
+    println!("{}:", "Unused cookbook versions".green().bold());
+    for (name, version) in &candidates {
+        println!("  {} {}", name, version);
+    }
+
+    if dry_run {
+        println!("\nDry run, {} versions would be deleted.", candidates.len());
+        return Ok(());
+    }
+
+    if !yes && !confirm(&format!("Delete {} cookbook versions?", candidates.len())) {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for (name, version) in &candidates {
+        let request_path = format!(
+            "/organizations/{}/cookbooks/{}/{}",
+            config.organization, name, version
+        );
+        match client::request::delete(config, &request_path).await {
+            Ok(r) if r.status == 200 => println!("Deleted {} {}", name, version),
+            Ok(r) => {
+                eprintln!("{} {}: HTTP Status: {}", name, version, r.status);
+                failed += 1;
+            }
+            Err(e) => {
+                eprintln!("{} {}: {}", name, version, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "\nDeleted {} of {} cookbook versions.",
+        candidates.len() - failed,
+        candidates.len()
+    );
+    if failed > 0 {
+        return Err(format!("cookbook prune: {} deletions failed", failed).into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_versions_numerically() {
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ordering::Equal);
+        assert_eq!(compare_versions("0.9", "1.0.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn pessimistic_bounds() {
+        assert_eq!(pessimistic_bound("1.2"), "2");
+        assert_eq!(pessimistic_bound("1.2.3"), "1.3");
+        assert_eq!(pessimistic_bound("1"), "2");
+    }
+
+    #[test]
+    fn satisfies_comparison_constraints() {
+        assert!(version_satisfies("1.2.0", "= 1.2.0"));
+        assert!(version_satisfies("1.2.0", "1.2"));
+        assert!(!version_satisfies("1.2.1", "= 1.2.0"));
+        assert!(version_satisfies("1.2.0", ">= 1.0"));
+        assert!(!version_satisfies("0.9.0", ">= 1.0"));
+        assert!(version_satisfies("2.0.0", "> 1.9.9"));
+        assert!(!version_satisfies("1.9.9", "> 1.9.9"));
+        assert!(version_satisfies("1.0.0", "<= 1.0"));
+        assert!(!version_satisfies("1.0.0", "< 1.0"));
+        assert!(version_satisfies("1.0.0", ">=0.0.0"));
+    }
+
+    #[test]
+    fn prune_keeps_dependencies_of_protected_versions() {
+        let cookbooks = BTreeMap::from([
+            (
+                "app".to_string(),
+                vec!["2.0.0".to_string(), "1.0.0".to_string()],
+            ),
+            (
+                "lib".to_string(),
+                vec![
+                    "3.0.0".to_string(),
+                    "1.1.0".to_string(),
+                    "1.0.0".to_string(),
+                ],
+            ),
+            (
+                "base".to_string(),
+                vec!["2.0.0".to_string(), "1.0.0".to_string()],
+            ),
+        ]);
+        let dependencies = CookbookDependencies::from([
+            (
+                "app".to_string(),
+                HashMap::from([
+                    (
+                        "2.0.0".to_string(),
+                        BTreeMap::from([("lib".to_string(), "= 1.0.0".to_string())]),
+                    ),
+                    (
+                        "1.0.0".to_string(),
+                        BTreeMap::from([("lib".to_string(), "~> 1.1".to_string())]),
+                    ),
+                ]),
+            ),
+            (
+                "lib".to_string(),
+                HashMap::from([(
+                    "1.0.0".to_string(),
+                    BTreeMap::from([("base".to_string(), "< 2.0".to_string())]),
+                )]),
+            ),
+        ]);
+        let protected = BTreeSet::from([
+            ("app".to_string(), "2.0.0".to_string()),
+            ("lib".to_string(), "3.0.0".to_string()),
+            ("base".to_string(), "2.0.0".to_string()),
+        ]);
+
+        assert_eq!(
+            prune_candidates(&cookbooks, protected, &dependencies),
+            vec![
+                ("app".to_string(), "1.0.0".to_string()),
+                ("lib".to_string(), "1.1.0".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn satisfies_pessimistic_constraints() {
+        assert!(version_satisfies("1.2.0", "~> 1.2"));
+        assert!(version_satisfies("1.9.5", "~> 1.2"));
+        assert!(!version_satisfies("2.0.0", "~> 1.2"));
+        assert!(!version_satisfies("1.1.9", "~> 1.2"));
+
+        assert!(version_satisfies("1.2.3", "~> 1.2.3"));
+        assert!(version_satisfies("1.2.10", "~> 1.2.3"));
+        assert!(!version_satisfies("1.3.0", "~> 1.2.3"));
+        assert!(!version_satisfies("1.2.2", "~> 1.2.3"));
+    }
+}
@@ -1,15 +1,27 @@
 use crate::{client, config::KnifeConfig, parse::traverse_json};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 
-#[derive(Serialize, Deserialize, Debug)]
-struct ChefEnvironment {
-    name: String,
-    description: String,
-    cookbook_versions: Value,
-    default_attributes: Value,
-    override_attributes: Value,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChefEnvironment {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    /// Cookbook version constraints, e.g. `"nginx": "~> 1.2"`
+    #[serde(default)]
+    pub cookbook_versions: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub default_attributes: Value,
+
+    #[serde(default)]
+    pub override_attributes: Value,
 }
 
 /// list - Lists environments
@@ -72,3 +84,69 @@ pub async fn show(config: &KnifeConfig, environment: &str) -> Result<(), Box<dyn
         Err(e) => Err(e),
     }
 }
+
+/// list_names - Fetches the names of all environments
+pub async fn list_names(config: &KnifeConfig) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/environments", config.organization);
+
+    let env = client::request::get(config, &request_path, "").await?;
+    match env.status {
+        200 => {
+            let environments: Value = serde_json::from_str(&env.body)?;
+            Ok(environments
+                .as_object()
+                .map(|m| m.keys().cloned().collect())
+                .unwrap_or_default())
+        }
+        _ => Err(format!("HTTP Status: {}", env.status).into()),
+    }
+}
+
+/// get_environment - Fetches a single environment from Chef Server
+pub async fn get_environment(
+    config: &KnifeConfig,
+    environment: &str,
+) -> Result<ChefEnvironment, Box<dyn Error + Send + Sync>> {
+    let request_path = format!(
+        "/organizations/{}/environments/{}",
+        config.organization, environment
+    );
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(env) => match env.status {
+            200 => match serde_json::from_str(&env.body) {
+                Ok(e) => Ok(e),
+                Err(e) => Err(format!("parsing environment {}: {}", environment, e).into()),
+            },
+            404 => Err(format!("Environment not found: {}", environment).into()),
+            _ => Err(format!("environment {}: HTTP Status: {}", environment, env.status).into()),
+        },
+        Err(e) => Err(format!("environment {}: {}", environment, e).into()),
+    }
+}
+
+/// Number of environments fetched at the same time by `fetch_all`
+const ENVIRONMENT_CONCURRENCY: usize = 10;
+
+/// fetch_all - Fetches every environment concurrently
+pub async fn fetch_all(
+    config: &KnifeConfig,
+) -> Result<Vec<ChefEnvironment>, Box<dyn Error + Send + Sync>> {
+    let semaphore = Arc::new(Semaphore::new(ENVIRONMENT_CONCURRENCY));
+    let mut handles = Vec::new();
+    for name in list_names(config).await? {
+        let config = config.clone();
+        let semaphore = Arc::clone(&semaphore);
+        handles.push(tokio::task::spawn(async move {
+            let _permit = semaphore.acquire().await;
+            get_environment(&config, &name).await
+        }));
+    }
+
+    let mut environments = Vec::new();
+    for h in handles {
+        environments.push(h.await??);
+    }
+
+    Ok(environments)
+}
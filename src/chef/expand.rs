@@ -0,0 +1,393 @@
+use crate::chef::role::{find_role, ChefRole};
+use crate::chef::run_list::RunListItem;
+use crate::config::KnifeConfig;
+use colored::Colorize;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Cache of roles fetched from Chef Server, keyed by role name
+pub type RoleCache = HashMap<String, ChefRole>;
+
+/// ExpansionNode - one entry of the expanded run list tree
+#[derive(Debug, Clone)]
+pub enum ExpansionNode {
+    Recipe {
+        name: String,
+        /// Already added earlier in the run list, chef-client skips it
+        duplicate: bool,
+    },
+    Role {
+        name: String,
+        /// Already expanded earlier in the run list, chef-client skips it
+        duplicate: bool,
+        children: Vec<ExpansionNode>,
+    },
+}
+
+/// RunListExpansion - result of expanding a run list for an environment the way chef-client
+/// does: recipes are de-duplicated keeping the first occurrence and every role is expanded once.
+#[derive(Debug, Clone, Default)]
+pub struct RunListExpansion {
+    /// Final recipe list with the role that contributed each recipe, `None` for the top level
+    pub recipes: Vec<(String, Option<String>)>,
+
+    /// Every role that was applied, in expansion order
+    pub roles: Vec<String>,
+
+    pub tree: Vec<ExpansionNode>,
+}
+
+impl RunListExpansion {
+    /// display - prints the expansion tree followed by the final recipe list
+    pub fn display(&self, title: &str) {
+        println!("{}", title.green().bold());
+        display_tree(&self.tree, "");
+
+        println!("\n{}:", "Recipes".green().bold());
+        for (recipe, role) in &self.recipes {
+            match role {
+                Some(role) => println!("  {} {}", recipe, format!("(role[{}])", role).dimmed()),
+                None => println!("  {}", recipe),
+            }
+        }
+    }
+}
+
+fn display_tree(nodes: &[ExpansionNode], prefix: &str) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i == nodes.len() - 1;
+        let branch = if last { "└── " } else { "├── " };
+
+        match node {
+            ExpansionNode::Recipe { name, duplicate } => {
+                let label = format!("recipe[{}]", name);
+                if *duplicate {
+                    println!(
+                        "{}{}{}",
+                        prefix,
+                        branch,
+                        format!("{} (skipped, duplicate)", label).dimmed()
+                    );
+                } else {
+                    println!("{}{}{}", prefix, branch, label);
+                }
+            }
+            ExpansionNode::Role {
+                name,
+                duplicate,
+                children,
+            } => {
+                let label = format!("role[{}]", name);
+                if *duplicate {
+                    println!(
+                        "{}{}{}",
+                        prefix,
+                        branch,
+                        format!("{} (skipped, already expanded)", label).dimmed()
+                    );
+                } else {
+                    println!("{}{}{}", prefix, branch, label.yellow());
+                }
+                let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                display_tree(children, &child_prefix);
+            }
+        }
+    }
+}
+
+/// role_run_list - run list a role applies in `env`, `env_run_lists` take priority over `run_list`
+pub fn role_run_list<'a>(role: &'a ChefRole, env: &str) -> &'a [String] {
+    match role.env_run_lists.get(env) {
+        Some(list) => list,
+        None => &role.run_list,
+    }
+}
+
+/// Number of roles fetched at the same time while expanding run lists
+const ROLE_CONCURRENCY: usize = 10;
+
+/// fetch_roles - Fetches every role reachable from `run_list` in `env` into `cache`. Roles of the
+/// same nesting level are fetched concurrently. Roles that don't exist on the server are
+/// returned instead of failing, `expand` reports them when it reaches them.
+pub async fn fetch_roles(
+    config: &KnifeConfig,
+    run_list: &[String],
+    env: &str,
+    cache: &mut RoleCache,
+) -> Result<BTreeSet<String>, Box<dyn Error + Send + Sync>> {
+    let mut missing = BTreeSet::new();
+    let mut pending = role_names(run_list);
+    let semaphore = Arc::new(Semaphore::new(ROLE_CONCURRENCY));
+
+    while !pending.is_empty() {
+        let mut handles = Vec::new();
+        for name in pending.drain(..) {
+            if cache.contains_key(&name) || missing.contains(&name) {
+                continue;
+            }
+            let config = config.clone();
+            let semaphore = Arc::clone(&semaphore);
+            handles.push(tokio::task::spawn(async move {
+                let _permit = semaphore.acquire().await;
+                let role = find_role(&config, &name).await;
+                (name, role)
+            }));
+        }
+
+        for h in handles {
+            let (name, role) = h.await?;
+            let Some(role) = role? else {
+                missing.insert(name);
+                continue;
+            };
+            for name in role_names(role_run_list(&role, env)) {
+                if !cache.contains_key(&name) && !pending.contains(&name) {
+                    pending.push(name);
+                }
+            }
+            cache.insert(role.name.clone(), role);
+        }
+    }
+
+    Ok(missing)
+}
+
+fn role_names(run_list: &[String]) -> Vec<String> {
+    run_list
+        .iter()
+        .filter_map(|i| match RunListItem::parse(i) {
+            RunListItem::Role(r) => Some(r),
+            RunListItem::Recipe(_) => None,
+        })
+        .collect()
+}
+
+/// recipe_key - `nginx` and `nginx::default` name the same recipe, the version pin is kept
+fn recipe_key(recipe: &str) -> String {
+    let (name, version) = match recipe.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (recipe, None),
+    };
+    let name = if name.contains("::") {
+        name.to_string()
+    } else {
+        format!("{}::default", name)
+    };
+    match version {
+        Some(v) => format!("{}@{}", name, v),
+        None => name,
+    }
+}
+
+/// expand - Expands `run_list` in `env` using already fetched roles. A role that includes itself,
+/// directly or through other roles, is reported as an error with the cycle path.
+pub fn expand(
+    run_list: &[String],
+    env: &str,
+    cache: &RoleCache,
+) -> Result<RunListExpansion, String> {
+    let mut expansion = RunListExpansion::default();
+    let mut stack = Vec::new();
+    expansion.tree = expand_items(run_list, env, cache, None, &mut stack, &mut expansion)?;
+    Ok(expansion)
+}
+
+fn expand_items(
+    run_list: &[String],
+    env: &str,
+    cache: &RoleCache,
+    parent: Option<&str>,
+    stack: &mut Vec<String>,
+    expansion: &mut RunListExpansion,
+) -> Result<Vec<ExpansionNode>, String> {
+    let mut nodes = Vec::new();
+
+    for item in run_list {
+        match RunListItem::parse(item) {
+            RunListItem::Recipe(name) => {
+                let key = recipe_key(&name);
+                let duplicate = expansion.recipes.iter().any(|(r, _)| recipe_key(r) == key);
+                if !duplicate {
+                    expansion
+                        .recipes
+                        .push((name.clone(), parent.map(String::from)));
+                }
+                nodes.push(ExpansionNode::Recipe { name, duplicate });
+            }
+
+            RunListItem::Role(name) => {
+                if stack.contains(&name) {
+                    stack.push(name);
+                    return Err(format!("role cycle detected: {}", stack.join(" -> ")));
+                }
+
+                if expansion.roles.contains(&name) {
+                    nodes.push(ExpansionNode::Role {
+                        name,
+                        duplicate: true,
+                        children: Vec::new(),
+                    });
+                    continue;
+                }
+
+                let role = match cache.get(&name) {
+                    Some(r) => r,
+                    None => return Err(format!("Role not found: {}", name)),
+                };
+
+                expansion.roles.push(name.clone());
+                stack.push(name.clone());
+                let children = expand_items(
+                    role_run_list(role, env),
+                    env,
+                    cache,
+                    Some(&name),
+                    stack,
+                    expansion,
+                )?;
+                stack.pop();
+
+                nodes.push(ExpansionNode::Role {
+                    name,
+                    duplicate: false,
+                    children,
+                });
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// expand_run_list - Fetches the needed roles and expands `run_list` for `env`
+pub async fn expand_run_list(
+    config: &KnifeConfig,
+    run_list: &[String],
+    env: &str,
+    cache: &mut RoleCache,
+) -> Result<RunListExpansion, Box<dyn Error + Send + Sync>> {
+    fetch_roles(config, run_list, env, cache).await?;
+    Ok(expand(run_list, env, cache)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> Vec<String> {
+        items.iter().map(|i| i.to_string()).collect()
+    }
+
+    fn role(name: &str, run_list: &[&str]) -> ChefRole {
+        let mut role = ChefRole::new(name);
+        role.run_list = list(run_list);
+        role
+    }
+
+    fn cache(roles: Vec<ChefRole>) -> RoleCache {
+        roles.into_iter().map(|r| (r.name.clone(), r)).collect()
+    }
+
+    fn recipes(expansion: &RunListExpansion) -> Vec<(&str, Option<&str>)> {
+        expansion
+            .recipes
+            .iter()
+            .map(|(r, role)| (r.as_str(), role.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn expands_nested_roles_in_order() {
+        let roles = cache(vec![
+            role("web", &["role[base]", "recipe[nginx]"]),
+            role("base", &["recipe[ntp]", "users"]),
+        ]);
+        let expansion = expand(&list(&["role[web]", "recipe[app]"]), "_default", &roles).unwrap();
+
+        assert_eq!(expansion.roles, vec!["web", "base"]);
+        assert_eq!(
+            recipes(&expansion),
+            vec![
+                ("ntp", Some("base")),
+                ("users", Some("base")),
+                ("nginx", Some("web")),
+                ("app", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_duplicate_recipes_and_roles() {
+        let roles = cache(vec![
+            role("web", &["role[base]", "recipe[ntp]"]),
+            role("base", &["recipe[ntp]"]),
+        ]);
+        let expansion = expand(&list(&["role[web]", "role[base]"]), "_default", &roles).unwrap();
+
+        assert_eq!(recipes(&expansion), vec![("ntp", Some("base"))]);
+        assert!(matches!(
+            &expansion.tree[1],
+            ExpansionNode::Role {
+                duplicate: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn default_recipe_matches_bare_cookbook_name() {
+        let expansion = expand(
+            &list(&[
+                "recipe[nginx]",
+                "nginx::default",
+                "nginx::ssl",
+                "nginx@1.2.0",
+            ]),
+            "_default",
+            &RoleCache::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            recipes(&expansion),
+            vec![("nginx", None), ("nginx::ssl", None), ("nginx@1.2.0", None)]
+        );
+    }
+
+    #[test]
+    fn uses_environment_run_lists() {
+        let mut web = role("web", &["recipe[nginx]"]);
+        web.env_run_lists
+            .insert("prod".to_string(), list(&["recipe[nginx]", "recipe[waf]"]));
+        let roles = cache(vec![web]);
+
+        let dev = expand(&list(&["role[web]"]), "dev", &roles).unwrap();
+        assert_eq!(recipes(&dev), vec![("nginx", Some("web"))]);
+
+        let prod = expand(&list(&["role[web]"]), "prod", &roles).unwrap();
+        assert_eq!(
+            recipes(&prod),
+            vec![("nginx", Some("web")), ("waf", Some("web"))]
+        );
+    }
+
+    #[test]
+    fn reports_cycles_and_missing_roles() {
+        let roles = cache(vec![
+            role("a", &["role[b]"]),
+            role("b", &["role[c]"]),
+            role("c", &["role[a]"]),
+        ]);
+        assert_eq!(
+            expand(&list(&["role[a]"]), "_default", &roles).unwrap_err(),
+            "role cycle detected: a -> b -> c -> a"
+        );
+
+        assert_eq!(
+            expand(&list(&["role[gone]"]), "_default", &roles).unwrap_err(),
+            "Role not found: gone"
+        );
+    }
+}
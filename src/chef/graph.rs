@@ -0,0 +1,249 @@
+use crate::chef::expand::{fetch_roles, role_run_list, RoleCache};
+use crate::chef::role::list_names;
+use crate::chef::run_list::RunListItem;
+use crate::chef::search::search_nodes;
+use crate::config::KnifeConfig;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+
+/// Output formats for `graph`
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum NodeKind {
+    Role,
+    /// Role referenced by a run list that doesn't exist on the server
+    MissingRole,
+    Recipe,
+    Cookbook,
+}
+
+/// RoleGraph - role, recipe and cookbook vertices keyed by `kind:name`, with edges optionally
+/// labelled by the environment of the `env_run_lists` entry they come from.
+#[derive(Debug, Default)]
+struct RoleGraph {
+    nodes: BTreeMap<String, (NodeKind, String)>,
+    edges: BTreeSet<(String, String, Option<String>)>,
+}
+
+impl RoleGraph {
+    fn add_node(&mut self, kind: NodeKind, name: &str, label: String) -> String {
+        let prefix = match kind {
+            NodeKind::Role | NodeKind::MissingRole => "role",
+            NodeKind::Recipe => "recipe",
+            NodeKind::Cookbook => "cookbook",
+        };
+        let key = format!("{}:{}", prefix, name);
+        self.nodes.entry(key.clone()).or_insert((kind, label));
+        key
+    }
+
+    /// add_run_list - adds the edges from `role` to every item of a run list
+    fn add_run_list(&mut self, role: &str, run_list: &[String], env: Option<&str>) {
+        let from = format!("role:{}", role);
+        for item in run_list {
+            let to = match RunListItem::parse(item) {
+                RunListItem::Role(r) => self.add_node(NodeKind::Role, &r, r.clone()),
+                RunListItem::Recipe(recipe) => {
+                    let recipe_key = self.add_node(NodeKind::Recipe, &recipe, recipe.clone());
+                    let cookbook = cookbook_name(&recipe);
+                    let cookbook_key =
+                        self.add_node(NodeKind::Cookbook, &cookbook, cookbook.clone());
+                    self.edges.insert((recipe_key.clone(), cookbook_key, None));
+                    recipe_key
+                }
+            };
+            self.edges.insert((from.clone(), to, env.map(String::from)));
+        }
+    }
+
+    /// mark_missing - turns the vertex of a role that doesn't exist into a missing role
+    fn mark_missing(&mut self, name: &str) {
+        self.nodes.insert(
+            format!("role:{}", name),
+            (NodeKind::MissingRole, format!("{} (missing)", name)),
+        );
+    }
+
+    fn render_dot(&self) -> String {
+        let mut out = String::from("digraph roles {\n  rankdir=LR;\n");
+        for (key, (kind, label)) in &self.nodes {
+            let shape = match kind {
+                NodeKind::Role => "box",
+                NodeKind::MissingRole => "box, style=dashed, color=red",
+                NodeKind::Recipe => "ellipse",
+                NodeKind::Cookbook => "folder",
+            };
+            out.push_str(&format!(
+                "  \"{}\" [label=\"{}\", shape={}];\n",
+                escape_dot(key),
+                escape_dot(label),
+                shape
+            ));
+        }
+        for (from, to, env) in &self.edges {
+            match env {
+                Some(env) => out.push_str(&format!(
+                    "  \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                    escape_dot(from),
+                    escape_dot(to),
+                    escape_dot(env)
+                )),
+                None => out.push_str(&format!(
+                    "  \"{}\" -> \"{}\";\n",
+                    escape_dot(from),
+                    escape_dot(to)
+                )),
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn render_mermaid(&self) -> String {
+        // Mermaid ids must be plain identifiers, so every vertex gets a generated one
+        let ids: HashMap<&String, String> = self
+            .nodes
+            .keys()
+            .enumerate()
+            .map(|(i, key)| (key, format!("n{}", i)))
+            .collect();
+
+        let mut out = String::from("graph LR\n");
+        for (key, (kind, label)) in &self.nodes {
+            let label = escape_mermaid(label);
+            let vertex = match kind {
+                NodeKind::Role => format!("[\"{}\"]", label),
+                NodeKind::MissingRole => format!("[\"{}\"]:::missing", label),
+                NodeKind::Recipe => format!("([\"{}\"])", label),
+                NodeKind::Cookbook => format!("[(\"{}\")]", label),
+            };
+            out.push_str(&format!("  {}{}\n", ids[key], vertex));
+        }
+        for (from, to, env) in &self.edges {
+            match env {
+                Some(env) => out.push_str(&format!(
+                    "  {} -->|\"{}\"| {}\n",
+                    ids[from],
+                    escape_mermaid(env),
+                    ids[to]
+                )),
+                None => out.push_str(&format!("  {} --> {}\n", ids[from], ids[to])),
+            }
+        }
+        if self
+            .nodes
+            .values()
+            .any(|(k, _)| *k == NodeKind::MissingRole)
+        {
+            out.push_str("  classDef missing stroke:#f00,stroke-dasharray:5 5\n");
+        }
+        out
+    }
+}
+
+/// cookbook_name - `nginx::default@1.2.0` belongs to the `nginx` cookbook
+fn cookbook_name(recipe: &str) -> String {
+    let recipe = recipe.split('@').next().unwrap_or(recipe);
+    recipe.split("::").next().unwrap_or(recipe).to_string()
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// escape_mermaid - Mermaid labels are quoted, quotes inside them use the `#quot;` entity
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+/// roles - Prints the role -> role and role -> recipe -> cookbook graph of every role on the
+/// server. With `env` only the run lists applied in that environment are used, otherwise the
+/// `env_run_lists` edges are added with the environment as the edge label. Roles a run list
+/// references that don't exist on the server are drawn as missing.
+pub async fn roles(
+    config: &KnifeConfig,
+    env: Option<&str>,
+    format: GraphFormat,
+    with_nodes: bool,
+) -> Result<(), Box<dyn Error>> {
+    let names = match list_names(config).await {
+        Ok(n) => n,
+        Err(e) => return Err(format!("graph roles: {e}").into()),
+    };
+
+    let run_list: Vec<String> = names.iter().map(|n| format!("role[{}]", n)).collect();
+    let mut cache = RoleCache::new();
+    if let Err(e) = fetch_roles(config, &run_list, env.unwrap_or("_default"), &mut cache).await {
+        return Err(format!("graph roles: {e}").into());
+    }
+
+    let mut node_counts: HashMap<String, usize> = HashMap::new();
+    if with_nodes {
+        let nodes = match search_nodes(config, "*:*").await {
+            Ok(n) => n,
+            Err(e) => return Err(format!("graph roles: {e}").into()),
+        };
+        for node in nodes {
+            for item in node.run_list {
+                if let RunListItem::Role(r) = RunListItem::parse(&item) {
+                    *node_counts.entry(r).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let mut graph = RoleGraph::default();
+    for name in &names {
+        let label = if with_nodes {
+            let count = node_counts.get(name).copied().unwrap_or(0);
+            format!("{} ({} nodes)", name, count)
+        } else {
+            name.clone()
+        };
+        graph.add_node(NodeKind::Role, name, label);
+    }
+
+    for name in &names {
+        let Some(role) = cache.get(name) else {
+            continue;
+        };
+
+        match env {
+            Some(env) => graph.add_run_list(name, role_run_list(role, env), None),
+            None => {
+                graph.add_run_list(name, &role.run_list, None);
+                for (env, list) in role
+                    .env_run_lists
+                    .iter()
+                    .filter(|(_, l)| **l != role.run_list)
+                {
+                    graph.add_run_list(name, list, Some(env));
+                }
+            }
+        }
+    }
+
+    // Run lists can still point at roles that were deleted since
+    let missing: Vec<String> = graph
+        .nodes
+        .iter()
+        .filter(|(key, (kind, _))| *kind == NodeKind::Role && key.starts_with("role:"))
+        .map(|(key, _)| key["role:".len()..].to_string())
+        .filter(|name| !names.contains(name))
+        .collect();
+    for name in &missing {
+        graph.mark_missing(name);
+    }
+
+    match format {
+        GraphFormat::Dot => print!("{}", graph.render_dot()),
+        GraphFormat::Mermaid => print!("{}", graph.render_mermaid()),
+    }
+
+    Ok(())
+}
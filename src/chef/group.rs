@@ -0,0 +1,223 @@
+use crate::{client, config::KnifeConfig, prompt::confirm};
+use colored::Colorize;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashSet};
+use std::error::Error;
+
+/// Kind of group member, `actor` is a user
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum GroupMemberType {
+    Actor,
+    Client,
+    Group,
+}
+
+/// ChefGroup - Group from `GET /organizations/ORG/groups/NAME`
+#[derive(Deserialize, Debug, Clone)]
+pub struct ChefGroup {
+    pub groupname: String,
+
+    #[serde(default)]
+    pub users: Vec<String>,
+
+    #[serde(default)]
+    pub clients: Vec<String>,
+
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl ChefGroup {
+    fn members_mut(&mut self, member_type: GroupMemberType) -> &mut Vec<String> {
+        match member_type {
+            GroupMemberType::Actor => &mut self.users,
+            GroupMemberType::Client => &mut self.clients,
+            GroupMemberType::Group => &mut self.groups,
+        }
+    }
+}
+
+/// list - Lists the groups of the organization
+pub async fn list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/groups", config.organization);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let groups: Value = serde_json::from_str(&r.body)?;
+                for (k, _) in groups.as_object().unwrap() {
+                    println!("{k}");
+                }
+                Ok(())
+            }
+            _ => Err(format!("group list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("group list: {e}").into()),
+    }
+}
+
+/// get_group - Fetches a group
+pub async fn get_group(
+    config: &KnifeConfig,
+    group: &str,
+) -> Result<ChefGroup, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/groups/{}", config.organization, group);
+
+    let r = client::request::get(config, &request_path, "").await?;
+    match r.status {
+        200 => Ok(serde_json::from_str(&r.body)?),
+        404 => Err(format!("Group not found: {}", group).into()),
+        _ => Err(format!("group {}: HTTP Status: {}", group, r.status).into()),
+    }
+}
+
+/// show - Shows the members of a group. With `recursive` nested groups are expanded to the
+/// users and clients that are effectively members.
+pub async fn show(
+    config: &KnifeConfig,
+    group: &str,
+    recursive: bool,
+) -> Result<(), Box<dyn Error>> {
+    let chef_group = match get_group(config, group).await {
+        Ok(g) => g,
+        Err(e) => return Err(format!("group show: {e}").into()),
+    };
+
+    if !recursive {
+        print_members("Users", chef_group.users.iter());
+        print_members("Clients", chef_group.clients.iter());
+        print_members("Groups", chef_group.groups.iter());
+        return Ok(());
+    }
+
+    let mut users = BTreeSet::new();
+    let mut clients = BTreeSet::new();
+    let mut seen = HashSet::from([chef_group.groupname.clone()]);
+    let mut pending = vec![chef_group];
+
+    while let Some(g) = pending.pop() {
+        users.extend(g.users);
+        clients.extend(g.clients);
+
+        for nested in g.groups {
+            if !seen.insert(nested.clone()) {
+                continue;
+            }
+            match get_group(config, &nested).await {
+                Ok(n) => pending.push(n),
+                Err(e) => return Err(format!("group show: {e}").into()),
+            }
+        }
+    }
+
+    let mut nested: Vec<&String> = seen.iter().filter(|g| g.as_str() != group).collect();
+    nested.sort();
+
+    print_members("Users", users.iter());
+    print_members("Clients", clients.iter());
+    print_members("Via groups", nested.into_iter());
+
+    Ok(())
+}
+
+fn print_members<'a>(label: &str, members: impl Iterator<Item = &'a String>) {
+    println!("{}:", label.green().bold());
+    for m in members {
+        println!("  {}", m);
+    }
+}
+
+/// create - Creates an empty group
+pub async fn create(config: &KnifeConfig, group: &str) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/groups", config.organization);
+    let body = serde_json::to_string(&json!({ "groupname": group }))?;
+
+    match client::request::post_body(config, &request_path, &body).await {
+        Ok(r) => match r.status {
+            201 => {
+                println!("Created group[{}]", group);
+                Ok(())
+            }
+            409 => Err(format!("Group already exists: {}", group).into()),
+            _ => Err(format!("group create: HTTP Status: {}: {}", r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("group create: {e}").into()),
+    }
+}
+
+/// delete - Deletes a group after confirmation
+pub async fn delete(config: &KnifeConfig, group: &str, yes: bool) -> Result<(), Box<dyn Error>> {
+    if !yes && !confirm(&format!("Do you really want to delete group {}?", group)) {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let request_path = format!("/organizations/{}/groups/{}", config.organization, group);
+
+    match client::request::delete(config, &request_path).await {
+        Ok(r) => match r.status {
+            200 => {
+                println!("Deleted group[{}]", group);
+                Ok(())
+            }
+            404 => Err(format!("Group not found: {}", group).into()),
+            _ => Err(format!("group delete: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("group delete: {e}").into()),
+    }
+}
+
+/// edit_members - Adds or removes a user, client or group from a group. The whole member list
+/// is sent back since Chef Server replaces it on update.
+pub async fn edit_members(
+    config: &KnifeConfig,
+    group: &str,
+    member_type: GroupMemberType,
+    member: &str,
+    add: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut chef_group = match get_group(config, group).await {
+        Ok(g) => g,
+        Err(e) => return Err(format!("group: {e}").into()),
+    };
+
+    let members = chef_group.members_mut(member_type);
+    let present = members.iter().any(|m| m == member);
+
+    if add == present {
+        println!("Group {} unchanged.", group);
+        return Ok(());
+    }
+
+    if add {
+        members.push(member.to_string());
+    } else {
+        members.retain(|m| m != member);
+    }
+
+    let request_path = format!("/organizations/{}/groups/{}", config.organization, group);
+    let body = serde_json::to_string(&json!({
+        "groupname": group,
+        "actors": {
+            "users": chef_group.users,
+            "clients": chef_group.clients,
+            "groups": chef_group.groups,
+        }
+    }))?;
+
+    match client::request::put(config, &request_path, &body).await {
+        Ok(r) => match r.status {
+            200 => {
+                if add {
+                    println!("Added {} to group {}", member, group);
+                } else {
+                    println!("Removed {} from group {}", member, group);
+                }
+                Ok(())
+            }
+            _ => Err(format!("group: HTTP Status: {}: {}", r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("group: {e}").into()),
+    }
+}
@@ -0,0 +1,200 @@
+use crate::chef::client::{generate_key_pair, KeyFile};
+use crate::{client, config::KnifeConfig, prompt::confirm};
+use chrono::{Duration, Utc};
+use colored::Colorize;
+use serde::Deserialize;
+use serde_json::json;
+use std::error::Error;
+
+/// Owner type of a key, clients live in the organization while users are server wide
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum KeyOwner {
+    Client,
+    User,
+}
+
+/// Entry of the `GET .../keys` listing
+#[derive(Deserialize, Debug)]
+struct KeyListEntry {
+    name: String,
+
+    #[serde(default)]
+    expired: bool,
+}
+
+/// A single key from `GET .../keys/NAME`
+#[derive(Deserialize, Debug)]
+struct ChefKey {
+    name: String,
+    public_key: String,
+    expiration_date: String,
+}
+
+fn keys_path(config: &KnifeConfig, owner: KeyOwner, name: &str) -> String {
+    match owner {
+        KeyOwner::Client => format!(
+            "/organizations/{}/clients/{}/keys",
+            config.organization, name
+        ),
+        KeyOwner::User => format!("/users/{}/keys", name),
+    }
+}
+
+/// list - Lists the keys of a client or user
+pub async fn list(config: &KnifeConfig, owner: KeyOwner, name: &str) -> Result<(), Box<dyn Error>> {
+    let request_path = keys_path(config, owner, name);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let keys: Vec<KeyListEntry> = serde_json::from_str(&r.body)?;
+                for key in keys {
+                    if key.expired {
+                        println!("{} {}", key.name, "(expired)".red());
+                    } else {
+                        println!("{}", key.name);
+                    }
+                }
+                Ok(())
+            }
+            404 => Err(format!("key list: {} not found", name).into()),
+            _ => Err(format!("key list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("key list: {e}").into()),
+    }
+}
+
+/// show - Shows a key with its expiration date and public key
+pub async fn show(
+    config: &KnifeConfig,
+    owner: KeyOwner,
+    name: &str,
+    key_name: &str,
+) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("{}/{}", keys_path(config, owner, name), key_name);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let key: ChefKey = serde_json::from_str(&r.body)?;
+                println!("{}:            {}", "Name".green().bold(), key.name);
+                println!(
+                    "{}: {}",
+                    "Expiration date".green().bold(),
+                    key.expiration_date
+                );
+                println!("{}:\n{}", "Public key".green().bold(), key.public_key);
+                Ok(())
+            }
+            404 => Err(format!("key show: key {} of {} not found", key_name, name).into()),
+            _ => Err(format!("key show: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("key show: {e}").into()),
+    }
+}
+
+/// create - Generates an RSA key pair locally, uploads the public half and writes the private
+/// key to `key_out`. Without `expires_in_days` the key never expires.
+pub async fn create(
+    config: &KnifeConfig,
+    owner: KeyOwner,
+    name: &str,
+    key_name: Option<&str>,
+    expires_in_days: Option<i64>,
+    key_out: &str,
+) -> Result<(), Box<dyn Error>> {
+    let key_name = match key_name {
+        Some(k) => k.to_string(),
+        None => format!("key-{}", Utc::now().format("%Y%m%d%H%M%S")),
+    };
+
+    let expiration_date = match expires_in_days {
+        Some(days) => (Utc::now() + Duration::days(days))
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string(),
+        None => "infinity".to_string(),
+    };
+
+    let (private_pem, public_pem) = generate_key_pair()?;
+
+    // Open the key file before uploading, the private half can't be recovered afterwards
+    let key_file = match KeyFile::open(key_out) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("key create: {}: {}", key_out, e).into()),
+    };
+
+    let request_path = keys_path(config, owner, name);
+    let body = serde_json::to_string(&json!({
+        "name": key_name,
+        "public_key": public_pem,
+        "expiration_date": expiration_date,
+    }))?;
+
+    let r = match client::request::post_body(config, &request_path, &body).await {
+        Ok(r) => r,
+        Err(e) => {
+            key_file.discard();
+            return Err(format!("key create: {e}").into());
+        }
+    };
+
+    if r.status != 201 {
+        key_file.discard();
+        return match r.status {
+            404 => Err(format!("key create: {} not found", name).into()),
+            409 => Err(format!("key create: {} already has a key {}", name, key_name).into()),
+            _ => Err(format!("key create: HTTP Status: {}: {}", r.status, r.body).into()),
+        };
+    }
+
+    println!(
+        "Created key {} for {}, expires: {}",
+        key_name, name, expiration_date
+    );
+    if let Err(e) = key_file.write(&private_pem) {
+        let e = e.to_string();
+        // Don't leave a key on the server nobody holds the private half of
+        let key_path = format!("{}/{}", request_path, key_name);
+        let _ = client::request::delete(config, &key_path).await;
+        return Err(format!(
+            "key create: writing {}: {}, key {} was deleted again",
+            key_out, e, key_name
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// delete - Deletes a key after confirmation
+pub async fn delete(
+    config: &KnifeConfig,
+    owner: KeyOwner,
+    name: &str,
+    key_name: &str,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !yes
+        && !confirm(&format!(
+            "Do you really want to delete key {} of {}?",
+            key_name, name
+        ))
+    {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let request_path = format!("{}/{}", keys_path(config, owner, name), key_name);
+
+    match client::request::delete(config, &request_path).await {
+        Ok(r) => match r.status {
+            200 => {
+                println!("Deleted key {} of {}", key_name, name);
+                Ok(())
+            }
+            404 => Err(format!("key delete: key {} of {} not found", key_name, name).into()),
+            _ => Err(format!("key delete: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("key delete: {e}").into()),
+    }
+}
@@ -1,8 +1,20 @@
+use crate::chef::client::delete_client;
+use crate::chef::environment::get_environment;
+use crate::chef::expand::{expand_run_list, RoleCache};
+use crate::chef::run_list::{self, RunListEdit};
+use crate::chef::search::partial_search;
+use crate::parse::{
+    deep_merge, delete_path, diff_json, display_json_diff, get_path, set_path, traverse_json,
+    JsonChange,
+};
+use crate::prompt::{confirm, confirm_count};
 use crate::{client, config::KnifeConfig};
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::{error::Error, process::Stdio};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::{error::Error, fs, process::Stdio};
+use tokio::sync::Semaphore;
 
 #[derive(Deserialize, Serialize, Debug, Eq, PartialEq, Clone)]
 pub struct ChefNode {
@@ -10,6 +22,12 @@ pub struct ChefNode {
     pub chef_environment: String,
     pub name: String,
     pub run_list: Vec<String>,
+
+    #[serde(default)]
+    pub policy_name: Option<String>,
+
+    #[serde(default)]
+    pub policy_group: Option<String>,
 }
 
 /// Collects relevant fields from the `automatic` key from Chef API response
@@ -65,6 +83,12 @@ impl ChefNode {
             "Chef Environment".green().bold(),
             self.chef_environment
         );
+        if let Some(policy_name) = &self.policy_name {
+            println!("{}:           {}", "Policy".green().bold(), policy_name);
+        }
+        if let Some(policy_group) = &self.policy_group {
+            println!("{}:     {}", "Policy group".green().bold(), policy_group);
+        }
         println!(
             "{}:            {}",
             "Roles".green().bold(),
@@ -113,8 +137,77 @@ pub async fn node_list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
     }
 }
 
-// node_show queries Chef server to display information about the node object
-pub async fn node_show(config: &KnifeConfig, node_id: &str) -> Result<(), Box<dyn Error>> {
+/// Attribute precedence levels of a node, `merged` combines them like chef-client does
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum AttributeLevel {
+    Automatic,
+    Default,
+    Normal,
+    Override,
+    Merged,
+}
+
+/// Precedence levels from lowest to highest
+const PRECEDENCE: [&str; 4] = ["default", "normal", "override", "automatic"];
+
+/// attributes - Returns the attributes of one precedence level of a full node document
+pub fn attributes(node: &Value, level: AttributeLevel) -> Value {
+    let key = match level {
+        AttributeLevel::Automatic => "automatic",
+        AttributeLevel::Default => "default",
+        AttributeLevel::Normal => "normal",
+        AttributeLevel::Override => "override",
+        AttributeLevel::Merged => {
+            let mut merged = json!({});
+            for level in PRECEDENCE {
+                deep_merge(&mut merged, &node[level]);
+            }
+            return merged;
+        }
+    };
+
+    match &node[key] {
+        Value::Null => json!({}),
+        v => v.clone(),
+    }
+}
+
+/// node_show queries Chef server to display information about the node object. Selected
+/// attributes, a precedence level or `long` show attributes from the full node document instead
+/// of the summary.
+pub async fn node_show(
+    config: &KnifeConfig,
+    node_id: &str,
+    attribute_paths: &[String],
+    level: Option<AttributeLevel>,
+    long: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !attribute_paths.is_empty() || level.is_some() || long {
+        let node = match get_node_json(config, node_id).await {
+            Ok(node) => node,
+            Err(e) => return Err(format!("node show: {e}").into()),
+        };
+        let attrs = attributes(&node, level.unwrap_or(AttributeLevel::Merged));
+
+        if attribute_paths.is_empty() {
+            traverse_json(&attrs, "");
+            return Ok(());
+        }
+
+        for path in attribute_paths {
+            match get_path(&attrs, path) {
+                Some(v @ (Value::Object(_) | Value::Array(_))) => {
+                    println!("{}:", path.green().bold());
+                    traverse_json(v, "  ");
+                }
+                Some(Value::String(s)) => println!("{}: {}", path.green().bold(), s),
+                Some(v) => println!("{}: {}", path.green().bold(), v),
+                None => println!("{}: {}", path.green().bold(), "(not set)".dimmed()),
+            }
+        }
+        return Ok(());
+    }
+
     let request_path = format!("/organizations/{}/nodes/{}", config.organization, node_id);
 
     match client::request::get(config, &request_path, "").await {
@@ -138,6 +231,829 @@ pub async fn node_show(config: &KnifeConfig, node_id: &str) -> Result<(), Box<dy
     }
 }
 
+/// get_node - Fetches a single node from Chef Server
+pub async fn get_node(
+    config: &KnifeConfig,
+    node_id: &str,
+) -> Result<ChefNode, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/nodes/{}", config.organization, node_id);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(n) => match n.status {
+            200 => match serde_json::from_str(&n.body) {
+                Ok(node) => Ok(node),
+                Err(e) => Err(format!("parsing node {}: {}", node_id, e).into()),
+            },
+            404 => Err(format!("Node not found: {}", node_id).into()),
+            _ => Err(format!("node {}: HTTP Status: {}", node_id, n.status).into()),
+        },
+        Err(e) => Err(format!("node {}: {}", node_id, e).into()),
+    }
+}
+
+/// node_expand - Expands the node run list through its roles for the node environment
+pub async fn node_expand(config: &KnifeConfig, node_id: &str) -> Result<(), Box<dyn Error>> {
+    let node = match get_node(config, node_id).await {
+        Ok(n) => n,
+        Err(e) => return Err(format!("node expand: {e}").into()),
+    };
+
+    let mut cache = RoleCache::new();
+    match expand_run_list(config, &node.run_list, &node.chef_environment, &mut cache).await {
+        Ok(expansion) => {
+            expansion.display(&format!("{} ({})", node.name, node.chef_environment));
+            Ok(())
+        }
+        Err(e) => Err(format!("node expand: {e}").into()),
+    }
+}
+
+/// Number of nodes fetched or saved at the same time by bulk node commands
+const NODE_CONCURRENCY: usize = 10;
+
+/// NodeTarget - The nodes a node command works on, one node by name or every node matching a
+/// search query.
+#[derive(Debug, Clone)]
+pub enum NodeTarget {
+    Node(String),
+    Query(String),
+}
+
+impl NodeTarget {
+    /// from_args - With `--query` no node name is given, so the first positional argument clap
+    /// read as the node name belongs to the remaining arguments.
+    pub fn from_args(
+        node_id: Option<String>,
+        query: Option<String>,
+        mut args: Vec<String>,
+    ) -> Result<(Self, Vec<String>), String> {
+        match (node_id, query) {
+            (Some(node_id), None) => Ok((NodeTarget::Node(node_id), args)),
+            (first, Some(query)) => {
+                if let Some(first) = first {
+                    args.insert(0, first);
+                }
+                Ok((NodeTarget::Query(query), args))
+            }
+            (None, None) => Err("a node name or --query is required".to_string()),
+        }
+    }
+
+    /// resolve - Returns the names of the targeted nodes
+    pub async fn resolve(&self, config: &KnifeConfig) -> Result<Vec<String>, Box<dyn Error>> {
+        match self {
+            NodeTarget::Node(name) => Ok(vec![name.clone()]),
+            NodeTarget::Query(query) => {
+                let keys = json!({ "name": ["name"] });
+                let rows = match partial_search(config, "node", query, &keys).await {
+                    Ok(rows) => rows,
+                    Err(e) => return Err(format!("search {}: {}", query, e).into()),
+                };
+
+                let mut names: Vec<String> = rows
+                    .iter()
+                    .filter_map(|r| r["name"].as_str().map(String::from))
+                    .collect();
+                names.sort();
+                Ok(names)
+            }
+        }
+    }
+}
+
+/// get_node_json - Fetches the full node document, keeping every attribute level so it can be
+/// saved back without losing data.
+pub async fn get_node_json(
+    config: &KnifeConfig,
+    node_id: &str,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/nodes/{}", config.organization, node_id);
+
+    let n = client::request::get(config, &request_path, "").await?;
+    match n.status {
+        200 => Ok(serde_json::from_str(&n.body)?),
+        404 => Err(format!("Node not found: {}", node_id).into()),
+        _ => Err(format!("node {}: HTTP Status: {}", node_id, n.status).into()),
+    }
+}
+
+/// put_node_json - Saves a full node document
+pub async fn put_node_json(
+    config: &KnifeConfig,
+    node_id: &str,
+    node: &Value,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/nodes/{}", config.organization, node_id);
+    let body = serde_json::to_string(node)?;
+
+    let n = client::request::put(config, &request_path, &body).await?;
+    match n.status {
+        200 => Ok(()),
+        404 => Err(format!("Node not found: {}", node_id).into()),
+        _ => Err(format!("node {}: HTTP Status: {}: {}", node_id, n.status, n.body).into()),
+    }
+}
+
+/// fetch_nodes_json - Fetches node documents concurrently, returning each node with its result
+async fn fetch_nodes_json(
+    config: &KnifeConfig,
+    names: Vec<String>,
+) -> Result<Vec<(String, Result<Value, Box<dyn Error + Send + Sync>>)>, Box<dyn Error>> {
+    let semaphore = Arc::new(Semaphore::new(NODE_CONCURRENCY));
+    let mut handles = Vec::new();
+
+    for name in names {
+        let config = config.clone();
+        let semaphore = Arc::clone(&semaphore);
+        handles.push(tokio::task::spawn(async move {
+            let _permit = semaphore.acquire().await;
+            let result = get_node_json(&config, &name).await;
+            (name, result)
+        }));
+    }
+
+    let mut nodes = Vec::new();
+    for h in handles {
+        nodes.push(h.await?);
+    }
+
+    Ok(nodes)
+}
+
+/// save_nodes_json - Saves node documents concurrently, printing the result for each node and
+/// returning the number of failures.
+async fn save_nodes_json(
+    config: &KnifeConfig,
+    nodes: Vec<(String, Value)>,
+) -> Result<usize, Box<dyn Error>> {
+    let semaphore = Arc::new(Semaphore::new(NODE_CONCURRENCY));
+    let mut handles = Vec::new();
+
+    for (name, node) in nodes {
+        let config = config.clone();
+        let semaphore = Arc::clone(&semaphore);
+        handles.push(tokio::task::spawn(async move {
+            let _permit = semaphore.acquire().await;
+            let result = put_node_json(&config, &name, &node).await;
+            (name, result)
+        }));
+    }
+
+    let mut failed = 0;
+    for h in handles {
+        let (name, result) = h.await?;
+        match result {
+            Ok(()) => println!("Saved node[{}]", name),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    Ok(failed)
+}
+
+/// edit_nodes - Applies `edit` to the full JSON of every targeted node and saves the nodes it
+/// changed. `edit` returns whether it changed the node and prints its own preview. Query targets
+/// are confirmed before saving unless `yes` is set.
+pub async fn edit_nodes<F>(
+    config: &KnifeConfig,
+    target: &NodeTarget,
+    yes: bool,
+    mut edit: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&str, &mut Value) -> Result<bool, String>,
+{
+    let names = target.resolve(config).await?;
+    if names.is_empty() {
+        println!("No nodes matched.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    let mut changed = Vec::new();
+
+    for (name, result) in fetch_nodes_json(config, names).await? {
+        let mut node = match result {
+            Ok(node) => node,
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                failed += 1;
+                continue;
+            }
+        };
+
+        match edit(&name, &mut node) {
+            Ok(true) => changed.push((name, node)),
+            Ok(false) => println!("node[{}] unchanged.", name),
+            Err(e) => {
+                eprintln!("{}: {}", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if !changed.is_empty() {
+        if matches!(target, NodeTarget::Query(_))
+            && !yes
+            && !confirm(&format!("Save {} nodes?", changed.len()))
+        {
+            println!("Aborted.");
+            return Ok(());
+        }
+
+        let saved = changed.len();
+        let save_failed = save_nodes_json(config, changed).await?;
+        failed += save_failed;
+
+        if matches!(target, NodeTarget::Query(_)) {
+            println!("Saved {} nodes, {} failed", saved - save_failed, failed);
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} nodes failed", failed).into());
+    }
+
+    Ok(())
+}
+
+/// run_list_edit - Changes the run list of the targeted nodes, showing the difference per node
+pub async fn run_list_edit(
+    config: &KnifeConfig,
+    target: &NodeTarget,
+    edit: &RunListEdit,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    if matches!(edit, RunListEdit::Set { items } if items.is_empty()) && !yes {
+        return Err("node run_list set: no items given, pass --yes to clear the run list".into());
+    }
+
+    let result = edit_nodes(config, target, yes, |name, node| {
+        let before: Vec<String> = match serde_json::from_value(node["run_list"].clone()) {
+            Ok(list) => list,
+            Err(e) => return Err(format!("reading run_list: {}", e)),
+        };
+
+        let after = edit.apply(&before)?;
+        if after == before {
+            return Ok(false);
+        }
+
+        run_list::display_diff(&format!("node[{}] run_list", name), &before, &after);
+        node["run_list"] = json!(after);
+        Ok(true)
+    })
+    .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("node run_list: {e}").into()),
+    }
+}
+
+/// environment_set - Moves the targeted nodes to an existing environment
+pub async fn environment_set(
+    config: &KnifeConfig,
+    target: &NodeTarget,
+    environment: &str,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    if let Err(e) = get_environment(config, environment).await {
+        return Err(format!("node environment set: {e}").into());
+    }
+
+    let result = edit_nodes(config, target, yes, |name, node| {
+        let before = node["chef_environment"].as_str().unwrap_or("_default");
+        if before == environment {
+            return Ok(false);
+        }
+
+        println!(
+            "node[{}]: {} -> {}",
+            name,
+            before.red(),
+            environment.green()
+        );
+        node["chef_environment"] = json!(environment);
+        Ok(true)
+    })
+    .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("node environment set: {e}").into()),
+    }
+}
+
+/// attribute_edit - Sets or deletes a dotted path in the `normal` attributes of the targeted
+/// nodes, deleting when `value` is None. The change is shown before the node is saved.
+pub async fn attribute_edit(
+    config: &KnifeConfig,
+    target: &NodeTarget,
+    path: &str,
+    value: Option<&Value>,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    let result = edit_nodes(config, target, yes, |name, node| {
+        let before = match &node["normal"] {
+            Value::Object(_) => node["normal"].clone(),
+            Value::Null => json!({}),
+            _ => return Err("normal attributes are not a hash".to_string()),
+        };
+
+        let mut after = before.clone();
+        match value {
+            Some(value) => set_path(&mut after, path, value.clone())?,
+            None => {
+                delete_path(&mut after, path);
+            }
+        }
+
+        let changes = diff_json(&before, &after, "normal");
+        if changes.is_empty() {
+            return Ok(false);
+        }
+
+        println!("{}:", format!("node[{}]", name).green().bold());
+        display_json_diff(&changes);
+        node["normal"] = after;
+        Ok(true)
+    })
+    .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("node attribute: {e}").into()),
+    }
+}
+
+/// order_sources - Orders the roles and the environment setting an attribute from lowest to
+/// highest precedence within a level: role defaults beat the environment default, while the
+/// environment override beats role overrides.
+fn order_sources<T>(level: &str, roles: Vec<T>, environment: Option<T>) -> Vec<T> {
+    let mut sources = Vec::new();
+    if level == "default" {
+        sources.extend(environment);
+        sources.extend(roles);
+    } else {
+        sources.extend(roles);
+        sources.extend(environment);
+    }
+    sources
+}
+
+/// winning_source - Index of the source whose value replaces all the others. Hashes and arrays
+/// are deep merged within a level, so no single source wins for them.
+fn winning_source(sources: &[(String, &Value)]) -> Option<usize> {
+    match sources.last() {
+        Some((_, v)) if !v.is_object() && !v.is_array() => Some(sources.len() - 1),
+        _ => None,
+    }
+}
+
+/// node_explain - Shows every precedence level that sets an attribute, and for default and
+/// override values which roles and environment supplied them. Sources are listed from lowest to
+/// highest precedence: at the default level the environment comes before roles, at the override
+/// level after them. Roles apply in expansion order.
+pub async fn node_explain(
+    config: &KnifeConfig,
+    node_id: &str,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let node = match get_node_json(config, node_id).await {
+        Ok(node) => node,
+        Err(e) => return Err(format!("node explain: {e}").into()),
+    };
+
+    let env = node["chef_environment"]
+        .as_str()
+        .unwrap_or("_default")
+        .to_string();
+    let run_list: Vec<String> =
+        serde_json::from_value(node["run_list"].clone()).unwrap_or_default();
+
+    let mut cache = RoleCache::new();
+    let expansion = match expand_run_list(config, &run_list, &env, &mut cache).await {
+        Ok(expansion) => expansion,
+        Err(e) => return Err(format!("node explain: {e}").into()),
+    };
+    let environment = match get_environment(config, &env).await {
+        Ok(environment) => environment,
+        Err(e) => return Err(format!("node explain: {e}").into()),
+    };
+
+    println!("{} on node[{}] ({})", path.green().bold(), node_id, env);
+
+    let mut winner = None;
+    for level in PRECEDENCE {
+        let value = get_path(&node[level], path);
+
+        let mut sources: Vec<(String, &Value)> = Vec::new();
+        if level == "default" || level == "override" {
+            let mut roles = Vec::new();
+            for role in &expansion.roles {
+                if let Some(r) = cache.get(role) {
+                    let attrs = match level {
+                        "default" => &r.default_attributes,
+                        _ => &r.override_attributes,
+                    };
+                    if let Some(v) = get_path(attrs, path) {
+                        roles.push((format!("role[{}]", role), v));
+                    }
+                }
+            }
+
+            let attrs = match level {
+                "default" => &environment.default_attributes,
+                _ => &environment.override_attributes,
+            };
+            let from_environment =
+                get_path(attrs, path).map(|v| (format!("environment[{}]", env), v));
+
+            sources = order_sources(level, roles, from_environment);
+        }
+
+        if value.is_none() && sources.is_empty() {
+            continue;
+        }
+
+        println!("{}:", level.green().bold());
+        match value {
+            Some(v) => println!("  node: {}", v),
+            None => println!("  node: {}", "(not set)".dimmed()),
+        }
+
+        let winner_index = winning_source(&sources);
+        for (i, (source, v)) in sources.iter().enumerate() {
+            match winner_index {
+                Some(w) if w == i => println!("  {}: {} {}", source, v, "(wins)".cyan()),
+                Some(_) => println!("  {}: {}", source, v.to_string().dimmed()),
+                None => println!("  {}: {}", source, v),
+            }
+        }
+
+        if value.is_some() {
+            if sources.is_empty() && level != "normal" && level != "automatic" {
+                println!("  {}", "(set by cookbook attributes)".dimmed());
+            }
+            winner = Some(level);
+        }
+    }
+
+    let merged = attributes(&node, AttributeLevel::Merged);
+    match (winner, get_path(&merged, path)) {
+        (Some(level), Some(value)) => println!(
+            "\n{}: {} (from {})",
+            "Merged value".green().bold(),
+            value,
+            level
+        ),
+        _ => println!("\n{} is not set on node[{}]", path, node_id),
+    }
+
+    Ok(())
+}
+
+/// new_node_json - Builds the document of a node that never ran chef-client
+fn new_node_json(name: &str, environment: &str, run_list: &[String]) -> Value {
+    json!({
+        "name": name,
+        "chef_type": "node",
+        "json_class": "Chef::Node",
+        "chef_environment": environment,
+        "run_list": run_list,
+        "normal": {},
+        "default": {},
+        "override": {},
+        "automatic": {},
+    })
+}
+
+/// post_node_json - Creates a node from a full node document
+async fn post_node_json(config: &KnifeConfig, node: &Value) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/nodes", config.organization);
+    let name = node["name"].as_str().unwrap_or_default();
+    let body = serde_json::to_string(node)?;
+
+    match client::request::post_body(config, &request_path, &body).await {
+        Ok(n) => match n.status {
+            201 => Ok(()),
+            409 => Err(format!("Node already exists: {}", name).into()),
+            _ => Err(format!("node {}: HTTP Status: {}: {}", name, n.status, n.body).into()),
+        },
+        Err(e) => Err(format!("node {}: {}", name, e).into()),
+    }
+}
+
+/// node_create - Creates an empty node in an existing environment
+pub async fn node_create(
+    config: &KnifeConfig,
+    node_id: &str,
+    environment: &str,
+    run_list: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let run_list = run_list::normalize(run_list)?;
+    if let Err(e) = get_environment(config, environment).await {
+        return Err(format!("node create: {e}").into());
+    }
+
+    match post_node_json(config, &new_node_json(node_id, environment, &run_list)).await {
+        Ok(()) => {
+            println!("Created node[{}]", node_id);
+            Ok(())
+        }
+        Err(e) => Err(format!("node create: {e}").into()),
+    }
+}
+
+/// node_from_file - Uploads nodes from JSON files, creating them when they don't exist yet.
+/// Missing attribute levels are filled in so partial documents can be used.
+pub async fn node_from_file(config: &KnifeConfig, files: &[String]) -> Result<(), Box<dyn Error>> {
+    for file in files {
+        let content = match fs::read_to_string(file) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("node from file: reading {}: {}", file, e).into()),
+        };
+        let mut node: Value = match serde_json::from_str(&content) {
+            Ok(node) => node,
+            Err(e) => return Err(format!("node from file: {}: {}", file, e).into()),
+        };
+
+        let name = match node["name"].as_str() {
+            Some(name) => name.to_string(),
+            None => return Err(format!("node from file: {}: missing name", file).into()),
+        };
+
+        let defaults = new_node_json(&name, "_default", &[]);
+        for (key, value) in defaults.as_object().unwrap() {
+            if node[key].is_null() {
+                node[key] = value.clone();
+            }
+        }
+
+        let request_path = format!("/organizations/{}/nodes/{}", config.organization, name);
+        let body = serde_json::to_string(&node)?;
+
+        // Chef Server answers 404 on PUT for a missing node, in which case it gets created
+        let result = match client::request::put(config, &request_path, &body).await {
+            Ok(n) => match n.status {
+                200 => Ok("Updated"),
+                404 => post_node_json(config, &node).await.map(|()| "Created"),
+                _ => Err(format!("node {}: HTTP Status: {}: {}", name, n.status, n.body).into()),
+            },
+            Err(e) => Err(format!("node {}: {}", name, e).into()),
+        };
+
+        match result {
+            Ok(action) => println!("{} node[{}]", action, name),
+            Err(e) => return Err(format!("node from file: {e}").into()),
+        }
+    }
+
+    Ok(())
+}
+
+/// node_clone - Creates a node with the run list, environment, policy and normal attributes of
+/// another node. Automatic, default and override attributes are left for chef-client to fill in.
+pub async fn node_clone(
+    config: &KnifeConfig,
+    source: &str,
+    destination: &str,
+) -> Result<(), Box<dyn Error>> {
+    let node = match get_node_json(config, source).await {
+        Ok(node) => node,
+        Err(e) => return Err(format!("node clone: {e}").into()),
+    };
+
+    let environment = node["chef_environment"].as_str().unwrap_or("_default");
+    let run_list: Vec<String> =
+        serde_json::from_value(node["run_list"].clone()).unwrap_or_default();
+
+    let mut clone = new_node_json(destination, environment, &run_list);
+    if node["normal"].is_object() {
+        clone["normal"] = node["normal"].clone();
+    }
+    for key in ["policy_name", "policy_group"] {
+        if node[key].is_string() {
+            clone[key] = node[key].clone();
+        }
+    }
+
+    match post_node_json(config, &clone).await {
+        Ok(()) => {
+            println!("Cloned node[{}] to node[{}]", source, destination);
+            Ok(())
+        }
+        Err(e) => Err(format!("node clone: {e}").into()),
+    }
+}
+
+/// delete_node - Deletes a node without asking, returning false when the node does not exist
+pub async fn delete_node(
+    config: &KnifeConfig,
+    node_id: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/nodes/{}", config.organization, node_id);
+
+    let n = client::request::delete(config, &request_path).await?;
+    match n.status {
+        200 => Ok(true),
+        404 => Ok(false),
+        _ => Err(format!("node {}: HTTP Status: {}", node_id, n.status).into()),
+    }
+}
+
+/// node_delete - Deletes nodes, and with `with_client` their API clients, concurrently. Deleting
+/// nodes matched by a query or more than one node asks to type the node count instead of a
+/// yes/no confirmation. A client is left alone when its node could not be deleted.
+pub async fn node_delete(
+    config: &KnifeConfig,
+    targets: &[NodeTarget],
+    with_client: bool,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut names = Vec::new();
+    for target in targets {
+        for name in target.resolve(config).await? {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    if names.is_empty() {
+        println!("No nodes matched.");
+        return Ok(());
+    }
+
+    let objects = if with_client {
+        "nodes and clients"
+    } else {
+        "nodes"
+    };
+
+    let by_query = targets.iter().any(|t| matches!(t, NodeTarget::Query(_)));
+
+    if !yes {
+        let confirmed = if names.len() == 1 && !by_query {
+            confirm(&format!(
+                "Do you really want to delete node {}{}?",
+                names[0],
+                if with_client { " and its client" } else { "" }
+            ))
+        } else {
+            println!("{}:", "Matched nodes".green().bold());
+            for name in &names {
+                println!("  {}", name);
+            }
+            confirm_count(&format!("Delete {} {}?", names.len(), objects), names.len())
+        };
+
+        if !confirmed {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    let semaphore = Arc::new(Semaphore::new(NODE_CONCURRENCY));
+    let mut handles = Vec::new();
+
+    for name in names {
+        let config = config.clone();
+        let semaphore = Arc::clone(&semaphore);
+        handles.push(tokio::task::spawn(async move {
+            let _permit = semaphore.acquire().await;
+            let node_result = delete_node(&config, &name).await;
+            // A node that is already gone still gets its client removed
+            let client_result = if with_client && node_result.is_ok() {
+                Some(delete_client(&config, &name).await)
+            } else {
+                None
+            };
+            (name, node_result, client_result)
+        }));
+    }
+
+    let (mut nodes_deleted, mut clients_deleted, mut failed) = (0, 0, 0);
+    for h in handles {
+        let (name, node_result, client_result) = h.await?;
+        match node_result {
+            Ok(true) => {
+                println!("Deleted node[{}]", name);
+                nodes_deleted += 1;
+            }
+            Ok(false) => {
+                eprintln!("node {}: Node not found", name);
+                failed += 1;
+            }
+            Err(e) => {
+                eprintln!("node {}: {}", name, e);
+                if with_client {
+                    eprintln!("client {}: skipped, the node was not deleted", name);
+                }
+                failed += 1;
+            }
+        }
+        match client_result {
+            Some(Ok(())) => {
+                println!("Deleted client[{}]", name);
+                clients_deleted += 1;
+            }
+            Some(Err(e)) => {
+                eprintln!("client {}: {}", name, e);
+                failed += 1;
+            }
+            None => {}
+        }
+    }
+
+    if with_client {
+        println!(
+            "Deleted {} nodes and {} clients, {} failed",
+            nodes_deleted, clients_deleted, failed
+        );
+    } else {
+        println!("Deleted {} nodes, {} failed", nodes_deleted, failed);
+    }
+
+    if failed > 0 {
+        return Err(format!("node delete: {} deletes failed", failed).into());
+    }
+
+    Ok(())
+}
+
+/// Attributes that differ between any two nodes and are skipped by `node diff` unless asked for
+const NOISY_ATTRIBUTES: [&str; 4] = ["ohai_time", "uptime", "uptime_seconds", "memory.free"];
+
+/// node_diff - Compares the attributes of two nodes at one precedence level, optionally below a
+/// dotted path. Without a path the environment and run list are compared as well.
+pub async fn node_diff(
+    config: &KnifeConfig,
+    node_a: &str,
+    node_b: &str,
+    level: AttributeLevel,
+    path: Option<&str>,
+    all: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (a, b) = match tokio::join!(get_node_json(config, node_a), get_node_json(config, node_b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(e), _) | (_, Err(e)) => return Err(format!("node diff: {e}").into()),
+    };
+
+    let mut changes = Vec::new();
+    if path.is_none() {
+        let summary = |node: &Value| {
+            json!({
+                "chef_environment": node["chef_environment"],
+                "run_list": node["run_list"],
+            })
+        };
+        changes.extend(diff_json(&summary(&a), &summary(&b), ""));
+    }
+
+    let (attrs_a, attrs_b) = (attributes(&a, level), attributes(&b, level));
+    let prefix = path.unwrap_or_default();
+    let (attrs_a, attrs_b) = match path {
+        Some(path) => (
+            get_path(&attrs_a, path).cloned().unwrap_or(Value::Null),
+            get_path(&attrs_b, path).cloned().unwrap_or(Value::Null),
+        ),
+        None => (attrs_a, attrs_b),
+    };
+    changes.extend(diff_json(&attrs_a, &attrs_b, prefix));
+
+    if !all {
+        changes.retain(|change| {
+            let changed_path = match change {
+                JsonChange::Added(p, _)
+                | JsonChange::Removed(p, _)
+                | JsonChange::Changed(p, ..) => p,
+            };
+            !NOISY_ATTRIBUTES
+                .iter()
+                .any(|k| changed_path == k || changed_path.starts_with(&format!("{}.", k)))
+        });
+    }
+
+    println!(
+        "{}",
+        format!("node[{}] -> node[{}]", node_a, node_b)
+            .green()
+            .bold()
+    );
+    if changes.is_empty() {
+        println!("  No differences");
+    } else {
+        display_json_diff(&changes);
+    }
+
+    Ok(())
+}
+
 /// node_ssh - handles the CLI call for opening a SSH connection
 pub async fn node_ssh(
     config: &KnifeConfig,
@@ -190,3 +1106,52 @@ async fn open_ssh_connection(
     println!("SSH process existed with: {}", status);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(sources: &[(String, &Value)]) -> Vec<String> {
+        sources.iter().map(|(s, _)| s.clone()).collect()
+    }
+
+    #[test]
+    fn role_defaults_beat_the_environment() {
+        let (a, b, e) = (json!(1), json!(2), json!(3));
+        let roles = vec![("role[a]".to_string(), &a), ("role[b]".to_string(), &b)];
+        let sources = order_sources(
+            "default",
+            roles,
+            Some(("environment[prod]".to_string(), &e)),
+        );
+        assert_eq!(
+            names(&sources),
+            vec!["environment[prod]", "role[a]", "role[b]"]
+        );
+        assert_eq!(winning_source(&sources), Some(2));
+    }
+
+    #[test]
+    fn environment_overrides_beat_roles() {
+        let (a, e) = (json!(1), json!(3));
+        let roles = vec![("role[a]".to_string(), &a)];
+        let sources = order_sources(
+            "override",
+            roles,
+            Some(("environment[prod]".to_string(), &e)),
+        );
+        assert_eq!(names(&sources), vec!["role[a]", "environment[prod]"]);
+        assert_eq!(winning_source(&sources), Some(1));
+    }
+
+    #[test]
+    fn merged_values_have_no_single_winner() {
+        let (a, e) = (json!(["ntp"]), json!({"servers": ["a"]}));
+        assert_eq!(winning_source(&[("role[a]".to_string(), &a)]), None);
+        assert_eq!(
+            winning_source(&[("environment[prod]".to_string(), &e)]),
+            None
+        );
+        assert_eq!(winning_source(&[]), None);
+    }
+}
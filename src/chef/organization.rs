@@ -0,0 +1,180 @@
+use crate::{client, config::KnifeConfig, prompt::confirm};
+use serde::Deserialize;
+use serde_json::json;
+use std::error::Error;
+
+/// Entry of `GET /organizations/ORG/users`
+#[derive(Deserialize, Debug)]
+struct OrgUserEntry {
+    user: OrgUser,
+}
+
+#[derive(Deserialize, Debug)]
+struct OrgUser {
+    username: String,
+}
+
+/// Pending invite from `GET /organizations/ORG/association_requests`
+#[derive(Deserialize, Debug)]
+struct AssociationRequest {
+    id: String,
+    username: String,
+}
+
+/// user_list - Lists the users that are members of the organization
+pub async fn user_list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/users", config.organization);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let users: Vec<OrgUserEntry> = serde_json::from_str(&r.body)?;
+                for entry in users {
+                    println!("{}", entry.user.username);
+                }
+                Ok(())
+            }
+            _ => Err(format!("org user list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("org user list: {e}").into()),
+    }
+}
+
+/// user_add - Adds a user to the organization directly, which needs a server admin. Regular
+/// admins invite the user instead.
+pub async fn user_add(config: &KnifeConfig, user: &str) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/users", config.organization);
+    let body = serde_json::to_string(&json!({ "username": user }))?;
+
+    match client::request::post_body(config, &request_path, &body).await {
+        Ok(r) => match r.status {
+            200 | 201 => {
+                println!("Added {} to {}", user, config.organization);
+                Ok(())
+            }
+            403 => Err(format!(
+                "org user add: Permission denied, use `org invite add {}` instead",
+                user
+            )
+            .into()),
+            404 => Err(format!("User not found: {}", user).into()),
+            409 => Err(format!("{} is already a member of {}", user, config.organization).into()),
+            _ => Err(format!("org user add: HTTP Status: {}: {}", r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("org user add: {e}").into()),
+    }
+}
+
+/// user_remove - Removes a user from the organization after confirmation
+pub async fn user_remove(
+    config: &KnifeConfig,
+    user: &str,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    if !yes
+        && !confirm(&format!(
+            "Do you really want to remove {} from {}?",
+            user, config.organization
+        ))
+    {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let request_path = format!("/organizations/{}/users/{}", config.organization, user);
+
+    match client::request::delete(config, &request_path).await {
+        Ok(r) => match r.status {
+            200 => {
+                println!("Removed {} from {}", user, config.organization);
+                Ok(())
+            }
+            404 => Err(format!("{} is not a member of {}", user, config.organization).into()),
+            _ => Err(format!("org user remove: HTTP Status: {}: {}", r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("org user remove: {e}").into()),
+    }
+}
+
+async fn pending_invites(
+    config: &KnifeConfig,
+) -> Result<Vec<AssociationRequest>, Box<dyn Error + Send + Sync>> {
+    let request_path = format!(
+        "/organizations/{}/association_requests",
+        config.organization
+    );
+
+    let r = client::request::get(config, &request_path, "").await?;
+    match r.status {
+        200 => Ok(serde_json::from_str(&r.body)?),
+        _ => Err(format!("HTTP Status: {}", r.status).into()),
+    }
+}
+
+/// invite_list - Lists pending association requests of the organization
+pub async fn invite_list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    match pending_invites(config).await {
+        Ok(invites) => {
+            for invite in invites {
+                println!("{}", invite.username);
+            }
+            Ok(())
+        }
+        Err(e) => Err(format!("org invite list: {e}").into()),
+    }
+}
+
+/// invite_add - Invites a user to join the organization
+pub async fn invite_add(config: &KnifeConfig, user: &str) -> Result<(), Box<dyn Error>> {
+    let request_path = format!(
+        "/organizations/{}/association_requests",
+        config.organization
+    );
+    let body = serde_json::to_string(&json!({ "user": user }))?;
+
+    match client::request::post_body(config, &request_path, &body).await {
+        Ok(r) => match r.status {
+            201 => {
+                println!("Invited {} to {}", user, config.organization);
+                Ok(())
+            }
+            404 => Err(format!("User not found: {}", user).into()),
+            409 => Err(format!(
+                "{} is already invited to or a member of {}",
+                user, config.organization
+            )
+            .into()),
+            _ => Err(format!("org invite add: HTTP Status: {}: {}", r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("org invite add: {e}").into()),
+    }
+}
+
+/// invite_cancel - Cancels the pending invite of a user
+pub async fn invite_cancel(config: &KnifeConfig, user: &str) -> Result<(), Box<dyn Error>> {
+    let invites = match pending_invites(config).await {
+        Ok(i) => i,
+        Err(e) => return Err(format!("org invite cancel: {e}").into()),
+    };
+
+    let invite = match invites.iter().find(|i| i.username == user) {
+        Some(i) => i,
+        None => return Err(format!("No pending invite for {}", user).into()),
+    };
+
+    let request_path = format!(
+        "/organizations/{}/association_requests/{}",
+        config.organization, invite.id
+    );
+
+    match client::request::delete(config, &request_path).await {
+        Ok(r) => match r.status {
+            200 => {
+                println!("Cancelled invite of {}", user);
+                Ok(())
+            }
+            _ => Err(format!("org invite cancel: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("org invite cancel: {e}").into()),
+    }
+}
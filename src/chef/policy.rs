@@ -0,0 +1,411 @@
+use crate::{client, config::KnifeConfig, prompt::confirm};
+use colored::Colorize;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+/// Entry of `GET /organizations/ORG/policies`
+#[derive(Deserialize, Debug, Default)]
+struct PolicyRevisions {
+    #[serde(default)]
+    revisions: BTreeMap<String, Value>,
+}
+
+/// PolicyGroup - Policy group with the revision pinned for each policy
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct PolicyGroup {
+    #[serde(default)]
+    pub policies: BTreeMap<String, PinnedRevision>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PinnedRevision {
+    pub revision_id: String,
+}
+
+/// PolicyLock - The parts of a Policyfile.lock.json revision souschef displays
+#[derive(Deserialize, Debug, Clone)]
+pub struct PolicyLock {
+    pub revision_id: String,
+    pub name: String,
+
+    #[serde(default)]
+    pub run_list: Vec<String>,
+
+    #[serde(default)]
+    pub cookbook_locks: BTreeMap<String, CookbookLock>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CookbookLock {
+    pub version: String,
+
+    #[serde(default)]
+    pub identifier: String,
+}
+
+impl PolicyLock {
+    pub fn display(&self) {
+        println!("{}:        {}", "Policy".green().bold(), self.name);
+        println!("{}:      {}", "Revision".green().bold(), self.revision_id);
+        println!(
+            "{}:      {}",
+            "Run List".green().bold(),
+            self.run_list.join(", ")
+        );
+        println!("{}:", "Cookbook locks".green().bold());
+        for (name, lock) in &self.cookbook_locks {
+            println!(
+                "  {} {} ({})",
+                name,
+                lock.version,
+                short_id(&lock.identifier)
+            );
+        }
+    }
+}
+
+/// short_id - Shortens revision ids and cookbook identifiers the way `chef show-policy` does
+pub fn short_id(id: &str) -> &str {
+    &id[..id.len().min(10)]
+}
+
+async fn get_policies(
+    config: &KnifeConfig,
+) -> Result<BTreeMap<String, PolicyRevisions>, Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/policies", config.organization);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => Ok(serde_json::from_str(&r.body)?),
+            _ => Err(format!("policy list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("policy list: {e}").into()),
+    }
+}
+
+/// get_policy_groups - Fetches every policy group with its pinned revisions
+pub async fn get_policy_groups(
+    config: &KnifeConfig,
+) -> Result<BTreeMap<String, PolicyGroup>, Box<dyn Error>> {
+    let request_path = format!("/organizations/{}/policy_groups", config.organization);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => Ok(serde_json::from_str(&r.body)?),
+            _ => Err(format!("policy group list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("policy group list: {e}").into()),
+    }
+}
+
+/// get_revision_json - Fetches a revision of a policy as the full lock document
+async fn get_revision_json(
+    config: &KnifeConfig,
+    policy: &str,
+    revision: &str,
+) -> Result<Value, Box<dyn Error>> {
+    let request_path = format!(
+        "/organizations/{}/policies/{}/revisions/{}",
+        config.organization, policy, revision
+    );
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => Ok(serde_json::from_str(&r.body)?),
+            404 => Err(format!("Revision {} of policy {} not found", revision, policy).into()),
+            _ => Err(format!("policy show: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("policy show: {e}").into()),
+    }
+}
+
+/// get_revision - Fetches a revision of a policy
+pub async fn get_revision(
+    config: &KnifeConfig,
+    policy: &str,
+    revision: &str,
+) -> Result<PolicyLock, Box<dyn Error>> {
+    let lock = get_revision_json(config, policy, revision).await?;
+    Ok(serde_json::from_value(lock)?)
+}
+
+/// diff_cookbook_locks - Prints the cookbooks whose locked version differs between two
+/// revisions, returning the number of differences.
+fn diff_cookbook_locks(from: &PolicyLock, to: &PolicyLock) -> usize {
+    let mut changes = 0;
+
+    for (name, lock) in &from.cookbook_locks {
+        match to.cookbook_locks.get(name) {
+            Some(other) if other.version == lock.version => {
+                if other.identifier != lock.identifier {
+                    println!(
+                        "  {}",
+                        format!(
+                            "~ {} {} ({} -> {})",
+                            name,
+                            lock.version,
+                            short_id(&lock.identifier),
+                            short_id(&other.identifier)
+                        )
+                        .yellow()
+                    );
+                    changes += 1;
+                }
+            }
+            Some(other) => {
+                println!(
+                    "  {}",
+                    format!("~ {} {} -> {}", name, lock.version, other.version).yellow()
+                );
+                changes += 1;
+            }
+            None => {
+                println!("  {}", format!("- {} {}", name, lock.version).red());
+                changes += 1;
+            }
+        }
+    }
+
+    for (name, lock) in &to.cookbook_locks {
+        if !from.cookbook_locks.contains_key(name) {
+            println!("  {}", format!("+ {} {}", name, lock.version).green());
+            changes += 1;
+        }
+    }
+
+    changes
+}
+
+/// list - Lists policies with their number of revisions
+pub async fn list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    for (name, policy) in get_policies(config).await? {
+        println!("{} ({} revisions)", name, policy.revisions.len());
+    }
+
+    Ok(())
+}
+
+/// show - Shows the revisions of a policy and the groups pinning them, or a single revision
+/// with its locked cookbooks.
+pub async fn show(
+    config: &KnifeConfig,
+    policy: &str,
+    revision: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(revision) = revision {
+        get_revision(config, policy, revision).await?.display();
+        return Ok(());
+    }
+
+    let request_path = format!("/organizations/{}/policies/{}", config.organization, policy);
+    let revisions: PolicyRevisions = match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => serde_json::from_str(&r.body)?,
+            404 => return Err(format!("Policy not found: {}", policy).into()),
+            _ => return Err(format!("policy show: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => return Err(format!("policy show: {e}").into()),
+    };
+
+    let groups = get_policy_groups(config).await?;
+
+    println!("{}:", policy.green().bold());
+    for revision_id in revisions.revisions.keys() {
+        let pinned_by: Vec<&str> = groups
+            .iter()
+            .filter(|(_, g)| {
+                g.policies
+                    .get(policy)
+                    .is_some_and(|p| &p.revision_id == revision_id)
+            })
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        if pinned_by.is_empty() {
+            println!("  {}", revision_id);
+        } else {
+            println!("  {} {}", revision_id, pinned_by.join(", ").cyan());
+        }
+    }
+
+    Ok(())
+}
+
+/// group_list - Lists policy groups with the revision pinned for each policy
+pub async fn group_list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    for (name, group) in get_policy_groups(config).await? {
+        println!("{}:", name.green().bold());
+        for (policy, pinned) in &group.policies {
+            println!("  {}: {}", policy, short_id(&pinned.revision_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// group_show - Shows every policy revision a group pins with its locked cookbooks
+pub async fn group_show(config: &KnifeConfig, group: &str) -> Result<(), Box<dyn Error>> {
+    let groups = get_policy_groups(config).await?;
+    let policy_group = match groups.get(group) {
+        Some(g) => g,
+        None => return Err(format!("Policy group not found: {}", group).into()),
+    };
+
+    for (policy, pinned) in &policy_group.policies {
+        get_revision(config, policy, &pinned.revision_id)
+            .await?
+            .display();
+        println!();
+    }
+
+    Ok(())
+}
+
+/// diff - Compares the revisions two policy groups pin, and the cookbook versions they lock
+pub async fn diff(
+    config: &KnifeConfig,
+    group_a: &str,
+    group_b: &str,
+    policy: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let groups = get_policy_groups(config).await?;
+    let (a, b) = match (groups.get(group_a), groups.get(group_b)) {
+        (Some(a), Some(b)) => (a, b),
+        (None, _) => return Err(format!("Policy group not found: {}", group_a).into()),
+        (_, None) => return Err(format!("Policy group not found: {}", group_b).into()),
+    };
+
+    let mut policies: Vec<&String> = a.policies.keys().chain(b.policies.keys()).collect();
+    policies.sort();
+    policies.dedup();
+    if let Some(policy) = policy {
+        policies.retain(|p| p.as_str() == policy);
+        if policies.is_empty() {
+            return Err(format!("Policy {} is not in {} or {}", policy, group_a, group_b).into());
+        }
+    }
+
+    for policy in policies {
+        match (a.policies.get(policy), b.policies.get(policy)) {
+            (Some(ra), Some(rb)) if ra.revision_id == rb.revision_id => {
+                println!(
+                    "{}: {} in both",
+                    policy.green().bold(),
+                    short_id(&ra.revision_id)
+                );
+            }
+            (Some(ra), Some(rb)) => {
+                println!(
+                    "{}: {} -> {}",
+                    policy.green().bold(),
+                    short_id(&ra.revision_id),
+                    short_id(&rb.revision_id)
+                );
+                let from = get_revision(config, policy, &ra.revision_id).await?;
+                let to = get_revision(config, policy, &rb.revision_id).await?;
+                if diff_cookbook_locks(&from, &to) == 0 {
+                    println!("  cookbook locks are identical");
+                }
+            }
+            (Some(ra), None) => println!(
+                "{}: {} only in {}",
+                policy.green().bold(),
+                short_id(&ra.revision_id),
+                group_a
+            ),
+            (None, Some(rb)) => println!(
+                "{}: {} only in {}",
+                policy.green().bold(),
+                short_id(&rb.revision_id),
+                group_b
+            ),
+            (None, None) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// promote - Pins a policy revision in a policy group. The cookbook changes against the
+/// currently pinned revision are shown before asking for confirmation.
+pub async fn promote(
+    config: &KnifeConfig,
+    policy: &str,
+    revision: &str,
+    group: &str,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    let lock = get_revision_json(config, policy, revision).await?;
+    let to: PolicyLock = serde_json::from_value(lock.clone())?;
+
+    let groups = get_policy_groups(config).await?;
+    let current = groups
+        .get(group)
+        .and_then(|g| g.policies.get(policy))
+        .map(|p| p.revision_id.clone());
+
+    match &current {
+        Some(current) if current == &to.revision_id => {
+            println!("{} already pins {} {}", group, policy, short_id(current));
+            return Ok(());
+        }
+        Some(current) => {
+            println!(
+                "{} {}: {} -> {}",
+                group.green().bold(),
+                policy,
+                short_id(current),
+                short_id(&to.revision_id)
+            );
+            let from = get_revision(config, policy, current).await?;
+            diff_cookbook_locks(&from, &to);
+        }
+        None => {
+            println!(
+                "{} {}: (none) -> {}",
+                group.green().bold(),
+                policy,
+                short_id(&to.revision_id)
+            );
+            for (name, cookbook) in &to.cookbook_locks {
+                println!("  {}", format!("+ {} {}", name, cookbook.version).green());
+            }
+        }
+    }
+
+    if !yes
+        && !confirm(&format!(
+            "Promote {} {} to {}?",
+            policy,
+            short_id(revision),
+            group
+        ))
+    {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let request_path = format!(
+        "/organizations/{}/policy_groups/{}/policies/{}",
+        config.organization, group, policy
+    );
+    let body = serde_json::to_string(&lock)?;
+
+    match client::request::put(config, &request_path, &body).await {
+        Ok(r) => match r.status {
+            200 | 201 => {
+                println!(
+                    "Promoted {} {} to {}",
+                    policy,
+                    short_id(&to.revision_id),
+                    group
+                );
+                Ok(())
+            }
+            _ => Err(format!("policy promote: HTTP Status: {}: {}", r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("policy promote: {e}").into()),
+    }
+}
@@ -1,27 +1,155 @@
-use crate::{client, config::KnifeConfig, parse::traverse_json};
+use crate::chef::expand::{expand_run_list, RoleCache};
+use crate::chef::run_list::{self, RunListEdit, RunListItem};
+use crate::chef::search::search_nodes;
+use crate::{client, config::KnifeConfig, parse::traverse_json, prompt::confirm, ruby};
+use colored::Colorize;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Serialize, Deserialize, Debug)]
-struct ChefRole {
-    name: String,
-    description: String,
-    run_list: Vec<String>,
-    default_attributes: Value,
-    override_attributes: Value,
-    env_run_lists: Value,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChefRole {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub run_list: Vec<String>,
+
+    #[serde(default = "empty_object")]
+    pub default_attributes: Value,
+
+    #[serde(default = "empty_object")]
+    pub override_attributes: Value,
+
+    #[serde(default)]
+    pub env_run_lists: BTreeMap<String, Vec<String>>,
+
+    #[serde(default = "role_json_class")]
+    pub json_class: String,
+
+    #[serde(default = "role_chef_type")]
+    pub chef_type: String,
 }
 
-/// lists roles
-pub async fn list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
-    let request_path = format!("/organizations/{}/roles", config.organization);
+fn empty_object() -> Value {
+    json!({})
+}
 
-    match client::request::get(config, &request_path, "").await {
-        Ok(r) => {
-            let roles: Value = serde_json::from_str(&r.body)?;
+fn role_json_class() -> String {
+    "Chef::Role".to_string()
+}
+
+fn role_chef_type() -> String {
+    "role".to_string()
+}
 
-            for (k, _) in roles.as_object().unwrap() {
+impl ChefRole {
+    pub fn new(name: &str) -> Self {
+        ChefRole {
+            name: name.to_string(),
+            description: String::new(),
+            run_list: Vec::new(),
+            default_attributes: empty_object(),
+            override_attributes: empty_object(),
+            env_run_lists: BTreeMap::new(),
+            json_class: role_json_class(),
+            chef_type: role_chef_type(),
+        }
+    }
+
+    /// from_dsl - Builds a role out of the `roles/*.rb` Ruby DSL
+    pub fn from_dsl(source: &str) -> Result<Self, Box<dyn Error>> {
+        let mut role = ChefRole::new("");
+
+        for call in ruby::parse_dsl(source)? {
+            let mut args = call.args.into_iter();
+            match call.method.as_str() {
+                "name" => role.name = dsl_string(args.next(), "name", call.line)?,
+                "description" => {
+                    role.description = dsl_string(args.next(), "description", call.line)?
+                }
+                "run_list" => role.run_list = dsl_run_list(args, call.line)?,
+                "env_run_lists" => {
+                    for arg in args {
+                        let Value::Object(envs) = arg else {
+                            return Err(format!(
+                                "line {}: env_run_lists expects a hash",
+                                call.line
+                            )
+                            .into());
+                        };
+                        for (env, items) in envs {
+                            let items = dsl_run_list(vec![items].into_iter(), call.line)?;
+                            role.env_run_lists.insert(env, items);
+                        }
+                    }
+                }
+                "default_attributes" => role.default_attributes = dsl_hash(args.next(), call.line)?,
+                "override_attributes" => {
+                    role.override_attributes = dsl_hash(args.next(), call.line)?
+                }
+                other => {
+                    return Err(format!(
+                        "line {}: unsupported role DSL method '{}'",
+                        call.line, other
+                    )
+                    .into())
+                }
+            }
+        }
+
+        if role.name.is_empty() {
+            return Err("role DSL does not set a name".into());
+        }
+
+        Ok(role)
+    }
+}
+
+fn dsl_string(arg: Option<Value>, method: &str, line: usize) -> Result<String, Box<dyn Error>> {
+    match arg {
+        Some(Value::String(s)) => Ok(s),
+        _ => Err(format!("line {}: {} expects a string", line, method).into()),
+    }
+}
+
+/// dsl_run_list - flattens `run_list "a", "b"` and `run_list ["a", "b"]` into a single list
+fn dsl_run_list(
+    args: impl Iterator<Item = Value>,
+    line: usize,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut run_list = Vec::new();
+    for arg in args {
+        match arg {
+            Value::String(s) => run_list.push(s),
+            Value::Array(items) => run_list.extend(dsl_run_list(items.into_iter(), line)?),
+            _ => return Err(format!("line {}: run list items must be strings", line).into()),
+        }
+    }
+    Ok(run_list)
+}
+
+fn dsl_hash(arg: Option<Value>, line: usize) -> Result<Value, Box<dyn Error>> {
+    match arg {
+        Some(v @ Value::Object(_)) => Ok(v),
+        None => Ok(empty_object()),
+        _ => Err(format!("line {}: attributes must be a hash", line).into()),
+    }
+}
+
+/// lists roles
+pub async fn list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    match list_names(config).await {
+        Ok(roles) => {
+            for k in roles {
                 println!("{k}")
             }
             Ok(())
@@ -31,6 +159,23 @@ pub async fn list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// list_names - Fetches the names of all roles
+pub async fn list_names(config: &KnifeConfig) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/roles", config.organization);
+
+    let r = client::request::get(config, &request_path, "").await?;
+    match r.status {
+        200 => {
+            let roles: Value = serde_json::from_str(&r.body)?;
+            Ok(roles
+                .as_object()
+                .map(|m| m.keys().cloned().collect())
+                .unwrap_or_default())
+        }
+        _ => Err(format!("HTTP Status: {}", r.status).into()),
+    }
+}
+
 /// show - Shows role settings
 pub async fn show(config: &KnifeConfig, role: &str) -> Result<(), Box<dyn Error>> {
     let request_path = format!("/organizations/{}/roles/{}", config.organization, role);
@@ -45,3 +190,414 @@ pub async fn show(config: &KnifeConfig, role: &str) -> Result<(), Box<dyn Error>
         Err(e) => Err(format!("role show: {e}").into()),
     }
 }
+
+/// get_role - Fetches a single role from Chef Server
+pub async fn get_role(
+    config: &KnifeConfig,
+    role: &str,
+) -> Result<ChefRole, Box<dyn Error + Send + Sync>> {
+    match find_role(config, role).await? {
+        Some(r) => Ok(r),
+        None => Err(format!("Role not found: {}", role).into()),
+    }
+}
+
+/// find_role - Fetches a single role, `None` when it doesn't exist
+pub async fn find_role(
+    config: &KnifeConfig,
+    role: &str,
+) -> Result<Option<ChefRole>, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/roles/{}", config.organization, role);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => match serde_json::from_str(&r.body) {
+                Ok(role) => Ok(Some(role)),
+                Err(e) => Err(format!("parsing role {}: {}", role, e).into()),
+            },
+            404 => Ok(None),
+            _ => Err(format!("role {}: HTTP Status: {}", role, r.status).into()),
+        },
+        Err(e) => Err(format!("role {}: {}", role, e).into()),
+    }
+}
+
+/// put_role - Saves an existing role on Chef Server
+pub async fn put_role(
+    config: &KnifeConfig,
+    role: &ChefRole,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/roles/{}", config.organization, role.name);
+    let body = serde_json::to_string(role)?;
+
+    match client::request::put(config, &request_path, &body).await {
+        Ok(r) => match r.status {
+            200 => Ok(()),
+            404 => Err(format!("Role not found: {}", role.name).into()),
+            _ => Err(format!("role {}: HTTP Status: {}: {}", role.name, r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("role {}: {}", role.name, e).into()),
+    }
+}
+
+/// post_role - Creates a new role on Chef Server
+async fn post_role(
+    config: &KnifeConfig,
+    role: &ChefRole,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/roles", config.organization);
+    let body = serde_json::to_string(role)?;
+
+    match client::request::post_body(config, &request_path, &body).await {
+        Ok(r) => match r.status {
+            201 => Ok(()),
+            409 => Err(format!("Role already exists: {}", role.name).into()),
+            _ => Err(format!("role {}: HTTP Status: {}: {}", role.name, r.status, r.body).into()),
+        },
+        Err(e) => Err(format!("role {}: {}", role.name, e).into()),
+    }
+}
+
+/// create - Creates a new role with an optional description and run list
+pub async fn create(
+    config: &KnifeConfig,
+    role: &str,
+    description: &str,
+    run_list: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let mut chef_role = ChefRole::new(role);
+    chef_role.description = description.to_string();
+    chef_role.run_list = run_list::normalize(run_list)?;
+
+    match post_role(config, &chef_role).await {
+        Ok(()) => {
+            println!("Created role[{}]", role);
+            Ok(())
+        }
+        Err(e) => Err(format!("role create: {e}").into()),
+    }
+}
+
+/// edit_file - Creates a new temporary file readable by the owner only. The name is unique so
+/// concurrent edits don't share a file, and `create_new` refuses to follow an existing path.
+fn edit_file(role: &str, content: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.subsec_nanos();
+    let path = std::env::temp_dir().join(format!(
+        "souschef-role-{}-{}-{}.json",
+        role,
+        std::process::id(),
+        nanos
+    ));
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&path)?;
+    file.write_all(content.as_bytes())?;
+
+    Ok(path)
+}
+
+/// save_edited_role - Validates the edited role JSON and uploads it, returning whether it changed
+async fn save_edited_role(
+    config: &KnifeConfig,
+    chef_role: &ChefRole,
+    content: &str,
+) -> Result<bool, Box<dyn Error>> {
+    let edited: ChefRole = match serde_json::from_str(content) {
+        Ok(r) => r,
+        Err(e) => return Err(format!("invalid role JSON: {e}").into()),
+    };
+
+    if edited.name != chef_role.name {
+        return Err("renaming a role is not supported".into());
+    }
+
+    if edited == *chef_role {
+        return Ok(false);
+    }
+
+    match put_role(config, &edited).await {
+        Ok(()) => Ok(true),
+        Err(e) => Err(e.to_string().into()),
+    }
+}
+
+/// edit - Opens the role JSON in $EDITOR and uploads the result when it changed. When the edited
+/// role can't be saved the file is kept so the changes aren't lost.
+pub async fn edit(config: &KnifeConfig, role: &str) -> Result<(), Box<dyn Error>> {
+    let chef_role = match get_role(config, role).await {
+        Ok(r) => r,
+        Err(e) => return Err(format!("role edit: {e}").into()),
+    };
+
+    let edit_path = match edit_file(role, &serde_json::to_string_pretty(&chef_role)?) {
+        Ok(p) => p,
+        Err(e) => return Err(format!("role edit: temporary file: {e}").into()),
+    };
+
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    let status = std::process::Command::new(&editor)
+        .arg(&edit_path)
+        .status()?;
+
+    let content = fs::read_to_string(&edit_path)?;
+
+    if !status.success() {
+        fs::remove_file(&edit_path)?;
+        return Err(format!("role edit: {} exited with {}", editor, status).into());
+    }
+
+    match save_edited_role(config, &chef_role, &content).await {
+        Ok(changed) => {
+            fs::remove_file(&edit_path)?;
+            if changed {
+                println!("Saved role[{}]", role);
+            } else {
+                println!("Role {} unchanged.", role);
+            }
+            Ok(())
+        }
+        Err(e) => Err(format!(
+            "role edit: {e}, your changes are kept in {}",
+            edit_path.display()
+        )
+        .into()),
+    }
+}
+
+/// delete - Deletes a role after confirmation
+pub async fn delete(config: &KnifeConfig, role: &str, yes: bool) -> Result<(), Box<dyn Error>> {
+    if !yes && !confirm(&format!("Do you really want to delete role {}?", role)) {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let request_path = format!("/organizations/{}/roles/{}", config.organization, role);
+
+    match client::request::delete(config, &request_path).await {
+        Ok(r) => match r.status {
+            200 => {
+                println!("Deleted role[{}]", role);
+                Ok(())
+            }
+            404 => Err(format!("Role not found: {}", role).into()),
+            _ => Err(format!("role delete: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("role delete: {e}").into()),
+    }
+}
+
+/// load_role_file - Reads a role from a `.json` or Ruby DSL `.rb` file
+pub fn load_role_file(path: &Path) -> Result<ChefRole, Box<dyn Error>> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return Err(format!("reading {}: {}", path.display(), e).into()),
+    };
+
+    let role = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&content).map_err(|e| e.to_string()),
+        Some("rb") => ChefRole::from_dsl(&content).map_err(|e| e.to_string()),
+        _ => Err("expected a .json or .rb file".to_string()),
+    };
+
+    role.map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+/// from_file - Uploads roles from files, creating them when they don't exist yet
+pub async fn from_file(config: &KnifeConfig, files: &[String]) -> Result<(), Box<dyn Error>> {
+    for file in files {
+        let role = load_role_file(Path::new(file))?;
+
+        let request_path = format!("/organizations/{}/roles/{}", config.organization, role.name);
+        let body = serde_json::to_string(&role)?;
+
+        // Chef Server answers 404 on PUT for a missing role, in which case it gets created
+        let result = match client::request::put(config, &request_path, &body).await {
+            Ok(r) => match r.status {
+                200 => Ok("Updated"),
+                404 => post_role(config, &role).await.map(|()| "Created"),
+                _ => {
+                    Err(format!("role {}: HTTP Status: {}: {}", role.name, r.status, r.body).into())
+                }
+            },
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(action) => println!("{} role[{}]", action, role.name),
+            Err(e) => return Err(format!("role from file: {e}").into()),
+        }
+    }
+
+    Ok(())
+}
+
+/// edit_run_list - Changes the role `run_list`, or the `env_run_lists` entry of `env`, shows the
+/// difference and saves the role.
+pub async fn edit_run_list(
+    config: &KnifeConfig,
+    role: &str,
+    edit: &RunListEdit,
+    env: Option<&str>,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    if matches!(edit, RunListEdit::Set { items } if items.is_empty()) && !yes {
+        return Err("role run_list set: no items given, pass --yes to clear the run list".into());
+    }
+
+    let mut chef_role = match get_role(config, role).await {
+        Ok(r) => r,
+        Err(e) => return Err(format!("role run_list: {e}").into()),
+    };
+
+    let (label, before) = match env {
+        Some(env) => match chef_role.env_run_lists.get(env) {
+            Some(list) => (format!("env_run_lists[{}]", env), list.clone()),
+            None if matches!(edit, RunListEdit::Remove { .. }) => {
+                return Err(format!("role run_list: {} has no run list for {}", role, env).into())
+            }
+            None => (format!("env_run_lists[{}]", env), Vec::new()),
+        },
+        None => ("run_list".to_string(), chef_role.run_list.clone()),
+    };
+
+    let after = match edit.apply(&before) {
+        Ok(list) => list,
+        Err(e) => return Err(format!("role run_list: {e}").into()),
+    };
+
+    if after == before {
+        println!("Run list of role[{}] unchanged.", role);
+        return Ok(());
+    }
+
+    run_list::display_diff(&label, &before, &after);
+
+    match env {
+        Some(env) => {
+            chef_role.env_run_lists.insert(env.to_string(), after);
+        }
+        None => chef_role.run_list = after,
+    }
+
+    match put_role(config, &chef_role).await {
+        Ok(()) => {
+            println!("Saved role[{}]", role);
+            Ok(())
+        }
+        Err(e) => Err(format!("role run_list: {e}").into()),
+    }
+}
+
+/// expand - Expands the role run list recursively for the environment and prints the tree
+pub async fn expand(config: &KnifeConfig, role: &str, env: &str) -> Result<(), Box<dyn Error>> {
+    let run_list = vec![format!("role[{}]", role)];
+    let mut cache = RoleCache::new();
+
+    match expand_run_list(config, &run_list, env, &mut cache).await {
+        Ok(expansion) => {
+            expansion.display(&format!("role[{}] ({})", role, env));
+            Ok(())
+        }
+        Err(e) => Err(format!("role expand: {e}").into()),
+    }
+}
+
+/// usage - Shows the nodes using a role grouped by environment, or a usage summary of every role
+/// when no role is given.
+pub async fn usage(config: &KnifeConfig, role: Option<&str>) -> Result<(), Box<dyn Error>> {
+    match role {
+        Some(role) => role_usage(config, role).await,
+        None => all_roles_usage(config).await,
+    }
+}
+
+async fn role_usage(config: &KnifeConfig, role: &str) -> Result<(), Box<dyn Error>> {
+    let query = format!("roles:{} OR run_list:role\\[{}\\]", role, role);
+    let nodes = match search_nodes(config, &query).await {
+        Ok(n) => n,
+        Err(e) => return Err(format!("role usage: {e}").into()),
+    };
+
+    if nodes.is_empty() {
+        println!("No nodes use role[{}]", role);
+        return Ok(());
+    }
+
+    let direct_item = format!("role[{}]", role);
+    let mut by_env: BTreeMap<&str, Vec<(&str, bool)>> = BTreeMap::new();
+    for node in &nodes {
+        by_env
+            .entry(&node.chef_environment)
+            .or_default()
+            .push((&node.name, node.run_list.contains(&direct_item)));
+    }
+
+    for (env, mut env_nodes) in by_env {
+        env_nodes.sort();
+        println!("{} ({}):", env.green().bold(), env_nodes.len());
+        for (name, direct) in env_nodes {
+            if direct {
+                println!("  {}", name);
+            } else {
+                println!("  {} {}", name, "(via nested role)".dimmed());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn all_roles_usage(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    let roles = match list_names(config).await {
+        Ok(r) => r,
+        Err(e) => return Err(format!("role usage: {e}").into()),
+    };
+
+    let nodes = match search_nodes(config, "*:*").await {
+        Ok(n) => n,
+        Err(e) => return Err(format!("role usage: {e}").into()),
+    };
+
+    // Direct use comes from the node run list, expanded use from the `roles` automatic
+    // attribute chef-client saves after expanding the run list.
+    let mut direct: HashMap<&str, usize> = HashMap::new();
+    let mut expanded: HashMap<&str, usize> = HashMap::new();
+    for node in &nodes {
+        for item in &node.run_list {
+            if let RunListItem::Role(r) = RunListItem::parse(item) {
+                if let Some(name) = roles.iter().find(|n| **n == r) {
+                    *direct.entry(name).or_default() += 1;
+                }
+            }
+        }
+        for r in &node.roles {
+            *expanded.entry(r).or_default() += 1;
+        }
+    }
+
+    let width = roles.iter().map(|r| r.len()).max().unwrap_or(4).max(4);
+    let header = format!("{:<width$}  {:>8}  {:>8}", "Role", "Direct", "Expanded");
+    println!("{}", header.green().bold());
+
+    let mut unused = Vec::new();
+    for role in &roles {
+        let d = direct.get(role.as_str()).copied().unwrap_or(0);
+        let e = expanded.get(role.as_str()).copied().unwrap_or(0);
+        println!("{:<width$}  {:>8}  {:>8}", role, d, e);
+        if d == 0 && e == 0 {
+            unused.push(role);
+        }
+    }
+
+    if !unused.is_empty() {
+        println!("\n{}:", "Unused roles".yellow().bold());
+        for role in unused {
+            println!("  {}", role);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,244 @@
+use colored::Colorize;
+
+/// Where new items go in a run list
+#[derive(Debug, Clone)]
+pub enum Position {
+    End,
+    Before(String),
+    After(String),
+}
+
+/// RunListEdit - a change requested against a role or node run list
+#[derive(Debug, Clone)]
+pub enum RunListEdit {
+    Add {
+        items: Vec<String>,
+        position: Position,
+    },
+    Remove {
+        items: Vec<String>,
+    },
+    Set {
+        items: Vec<String>,
+    },
+}
+
+/// normalize_item - turns a bare `cookbook` or `cookbook::recipe` into `recipe[...]`, leaving
+/// `recipe[...]` and `role[...]` entries untouched.
+pub fn normalize_item(item: &str) -> String {
+    let item = item.trim();
+    if (item.starts_with("recipe[") || item.starts_with("role[")) && item.ends_with(']') {
+        item.to_string()
+    } else {
+        format!("recipe[{}]", item)
+    }
+}
+
+/// normalize - normalizes a list of run list items and rejects duplicates
+pub fn normalize(items: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for item in items {
+        let item = normalize_item(item);
+        if normalized.contains(&item) {
+            return Err(format!("{} is listed more than once", item));
+        }
+        normalized.push(item);
+    }
+    Ok(normalized)
+}
+
+impl RunListEdit {
+    /// apply - applies the edit to `run_list`, returning the new run list
+    pub fn apply(&self, run_list: &[String]) -> Result<Vec<String>, String> {
+        match self {
+            RunListEdit::Add { items, position } => {
+                if items.is_empty() {
+                    return Err("no run list items given".to_string());
+                }
+                let items = normalize(items)?;
+                if let Some(dup) = items.iter().find(|i| run_list.contains(i)) {
+                    return Err(format!("{} is already in the run list", dup));
+                }
+
+                let index = match position {
+                    Position::End => run_list.len(),
+                    Position::Before(anchor) => find(run_list, anchor)?,
+                    Position::After(anchor) => find(run_list, anchor)? + 1,
+                };
+
+                let mut new_list = run_list.to_vec();
+                new_list.splice(index..index, items);
+                Ok(new_list)
+            }
+
+            RunListEdit::Remove { items } => {
+                if items.is_empty() {
+                    return Err("no run list items given".to_string());
+                }
+                let items = normalize(items)?;
+                if let Some(missing) = items.iter().find(|i| !run_list.contains(i)) {
+                    return Err(format!("{} is not in the run list", missing));
+                }
+
+                Ok(run_list
+                    .iter()
+                    .filter(|i| !items.contains(i))
+                    .cloned()
+                    .collect())
+            }
+
+            RunListEdit::Set { items } => normalize(items),
+        }
+    }
+}
+
+fn find(run_list: &[String], anchor: &str) -> Result<usize, String> {
+    let anchor = normalize_item(anchor);
+    run_list
+        .iter()
+        .position(|i| *i == anchor)
+        .ok_or(format!("{} is not in the run list", anchor))
+}
+
+/// display_diff - prints the run list change with removed items in red and added ones in green
+pub fn display_diff(label: &str, before: &[String], after: &[String]) {
+    println!("{}:", label.green().bold());
+    for item in before.iter().filter(|i| !after.contains(i)) {
+        println!("  {}", format!("- {}", item).red());
+    }
+    for item in after {
+        if before.contains(item) {
+            println!("    {}", item);
+        } else {
+            println!("  {}", format!("+ {}", item).green());
+        }
+    }
+}
+
+/// RunListItem - a parsed run list entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunListItem {
+    Recipe(String),
+    Role(String),
+}
+
+impl RunListItem {
+    /// parse - reads `recipe[name]`, `role[name]` or a bare recipe name
+    pub fn parse(item: &str) -> Self {
+        let item = item.trim();
+        if let Some(role) = item.strip_prefix("role[").and_then(|r| r.strip_suffix(']')) {
+            return RunListItem::Role(role.to_string());
+        }
+
+        match item
+            .strip_prefix("recipe[")
+            .and_then(|r| r.strip_suffix(']'))
+        {
+            Some(recipe) => RunListItem::Recipe(recipe.to_string()),
+            None => RunListItem::Recipe(item.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> Vec<String> {
+        items.iter().map(|i| i.to_string()).collect()
+    }
+
+    fn run_list() -> Vec<String> {
+        list(&["recipe[base]", "role[web]"])
+    }
+
+    #[test]
+    fn add_normalizes_and_appends() {
+        let edit = RunListEdit::Add {
+            items: list(&["nginx::default"]),
+            position: Position::End,
+        };
+        assert_eq!(
+            edit.apply(&run_list()).unwrap(),
+            list(&["recipe[base]", "role[web]", "recipe[nginx::default]"])
+        );
+    }
+
+    #[test]
+    fn add_before_and_after_an_item() {
+        let before = RunListEdit::Add {
+            items: list(&["role[db]"]),
+            position: Position::Before("role[web]".to_string()),
+        };
+        assert_eq!(
+            before.apply(&run_list()).unwrap(),
+            list(&["recipe[base]", "role[db]", "role[web]"])
+        );
+
+        let after = RunListEdit::Add {
+            items: list(&["ntp", "role[db]"]),
+            position: Position::After("base".to_string()),
+        };
+        assert_eq!(
+            after.apply(&run_list()).unwrap(),
+            list(&["recipe[base]", "recipe[ntp]", "role[db]", "role[web]"])
+        );
+    }
+
+    #[test]
+    fn add_rejects_bad_edits() {
+        let duplicate = RunListEdit::Add {
+            items: list(&["base"]),
+            position: Position::End,
+        };
+        assert!(duplicate.apply(&run_list()).is_err());
+
+        let missing_anchor = RunListEdit::Add {
+            items: list(&["ntp"]),
+            position: Position::After("role[db]".to_string()),
+        };
+        assert!(missing_anchor.apply(&run_list()).is_err());
+
+        let repeated = RunListEdit::Add {
+            items: list(&["ntp", "recipe[ntp]"]),
+            position: Position::End,
+        };
+        assert!(repeated.apply(&run_list()).is_err());
+
+        let empty = RunListEdit::Add {
+            items: Vec::new(),
+            position: Position::End,
+        };
+        assert!(empty.apply(&run_list()).is_err());
+    }
+
+    #[test]
+    fn remove_items() {
+        let edit = RunListEdit::Remove {
+            items: list(&["base"]),
+        };
+        assert_eq!(edit.apply(&run_list()).unwrap(), list(&["role[web]"]));
+
+        let missing = RunListEdit::Remove {
+            items: list(&["role[db]"]),
+        };
+        assert!(missing.apply(&run_list()).is_err());
+
+        let empty = RunListEdit::Remove { items: Vec::new() };
+        assert!(empty.apply(&run_list()).is_err());
+    }
+
+    #[test]
+    fn set_replaces_the_run_list() {
+        let edit = RunListEdit::Set {
+            items: list(&["role[db]", "ntp"]),
+        };
+        assert_eq!(
+            edit.apply(&run_list()).unwrap(),
+            list(&["role[db]", "recipe[ntp]"])
+        );
+
+        let clear = RunListEdit::Set { items: Vec::new() };
+        assert!(clear.apply(&run_list()).unwrap().is_empty());
+    }
+}
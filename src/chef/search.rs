@@ -2,6 +2,7 @@ use crate::client;
 use crate::config::KnifeConfig;
 use colored::Colorize;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::error::Error;
 use std::fmt;
 
@@ -15,6 +16,7 @@ enum NodeAttribute {
     Platform,
     Roles,
     Recipes,
+    Tags,
 }
 
 /// Display implementation to give a &str value to a Enum type
@@ -28,6 +30,7 @@ impl std::fmt::Display for NodeAttribute {
             NodeAttribute::Platform => "Platform",
             NodeAttribute::Roles => "Roles",
             NodeAttribute::Recipes => "Recipes",
+            NodeAttribute::Tags => "Tags",
         };
 
         write!(f, "{}", display_str)
@@ -45,6 +48,7 @@ impl NodeAttribute {
             "platform" => Some(Self::Platform),
             "roles" => Some(Self::Roles),
             "recipes" => Some(Self::Recipes),
+            "tags" => Some(Self::Tags),
             _ => None,
         }
     }
@@ -59,6 +63,7 @@ impl NodeAttribute {
             Self::Roles => node.display_roles(),
             Self::Platform => node.display_platform(),
             Self::Recipes => node.display_recipes(),
+            Self::Tags => node.display_tags(),
         }
     }
 }
@@ -131,6 +136,10 @@ pub struct SearchNode {
     /// List of roles assigned to the node
     #[serde(default)]
     pub roles: Vec<String>,
+
+    /// Node tags
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl SearchNode {
@@ -206,6 +215,14 @@ impl SearchNode {
         );
     }
 
+    fn display_tags(&self) {
+        println!(
+            "{}:             {}",
+            "Tags".green().bold(),
+            self.tags.join(", ")
+        );
+    }
+
     fn display_platform_family(&self) {
         println!(
             "{}:          {}",
@@ -223,18 +240,6 @@ impl SearchNode {
     }
 }
 
-// Chef Search POST mapping body.
-#[derive(Serialize, Deserialize)]
-pub struct ChefSearchResponseRaw {
-    #[serde(default)]
-    pub total: i32,
-
-    #[serde(default)]
-    pub start: i32,
-
-    pub rows: Vec<ChefNodeRowRaw>,
-}
-
 /// Row mapping, cotains the URL and data fields
 #[derive(Serialize, Deserialize)]
 pub struct ChefNodeRowRaw {
@@ -286,6 +291,9 @@ pub struct NodeDocumentRaw {
 
     #[serde(default)]
     pub roles: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
 }
 
 /// Implement From<> to convert the multi-layer `ChefNodeRowRaw` into a flat `SearchNode` struct
@@ -307,6 +315,7 @@ impl From<ChefNodeRowRaw> for SearchNode {
             platform_version: raw.data.platform_version.unwrap_or_default(),
             recipes: raw.data.recipes.unwrap_or_default(),
             roles: raw.data.roles.unwrap_or_default(),
+            tags: raw.data.tags.unwrap_or_default(),
         }
     }
 }
@@ -323,41 +332,126 @@ pub async fn display_search_nodes(config: &KnifeConfig, query: &str, attributes:
     }
 }
 
-/// search_nodes - Calls Chef server and issues as search for the node objects
+/// node_search_keys - partial search body mapping the `SearchNode` fields to node attributes
+fn node_search_keys() -> Value {
+    json!({
+        "node_name": ["name"],
+        "chef_environment": ["chef_environment"],
+        "hostname": ["hostname"],
+        "ipaddress": ["ipaddress"],
+        "platform_family": ["platform_family"],
+        "platform_version": ["platform_version"],
+        "roles": ["roles"],
+        "run_list": ["run_list"],
+        "macaddress": ["macaddress"],
+        "os": ["os"],
+        "os_version": ["os_version"],
+        "fqdn": ["fqdn"],
+        "platform": ["platform_version"],
+        "recipes": ["recipes"],
+        "tags": ["tags"]
+    })
+}
+
+/// search_nodes - Calls Chef server and issues as search for the node objects, reading every
+/// page of results
 pub async fn search_nodes(
     config: &KnifeConfig,
     query: &str,
 ) -> Result<Vec<SearchNode>, Box<dyn Error + Send + Sync>> {
-    let request_path = format!("/organizations/{}/search/node", config.organization);
+    let rows = search_rows(config, "node", query, &node_search_keys()).await?;
+
+    let mut nodes = Vec::new();
+    for row in rows {
+        match serde_json::from_value::<ChefNodeRowRaw>(row) {
+            Ok(row) => nodes.push(SearchNode::from(row)),
+            Err(e) => return Err(format!("parsing return JSON: {}", e).into()),
+        }
+    }
 
-    match client::request::post(config, &request_path, query).await {
-        Ok(k) => match k.status {
-            200 => {
-                let body: ChefSearchResponseRaw = match serde_json::from_str(&k.body) {
-                    Ok(body) => body,
+    Ok(nodes)
+}
+
+/// search_rows - Runs a partial search page by page until `total` rows were read, returning the
+/// raw rows. Fails when the server returns fewer rows than it reported, callers such as prune
+/// must never act on an incomplete node list.
+async fn search_rows(
+    config: &KnifeConfig,
+    index: &str,
+    query: &str,
+    keys: &Value,
+) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+    let request_path = format!("/organizations/{}/search/{}", config.organization, index);
+
+    let mut rows: Vec<Value> = Vec::new();
+    let mut total: usize;
+    loop {
+        let k = match client::request::post(config, &request_path, query, rows.len(), keys).await {
+            Ok(k) => k,
+            Err(e) => return Err(format!("search {}: {}", query, e).into()),
+        };
+
+        let mut page: Value =
+            match k.status {
+                200 => match serde_json::from_str(&k.body) {
+                    Ok(page) => page,
                     Err(e) => return Err(format!("parsing return JSON: {}", e).into()),
-                };
-
-                let nodes: Vec<SearchNode> = body.rows.into_iter().map(SearchNode::from).collect();
-
-                Ok(nodes)
-            }
-
-            400 => Err(
-                "HTTP Status: 400 - Request parameters or body have missing or invalid fields"
-                    .into(),
-            ),
-
-            401 => Err("HTTP Status: 401 - failed authentication!".into()),
-            403 => Err("HTTP Code: 403 . Permission denied".into()),
-            404 => Err("HTTP Code: 404 . Resource does not exist.".into()),
-            406 => Err("HTTP Code: 406. Accept header does not include application/json".into()),
-            _ => {
-                println!("HTTP Status code: {}", k.status);
-                println!("Body returned: {:#?}", k.body);
-                Err(format!("HTTP Status: {}", k.status).into())
-            }
-        },
-        Err(e) => Err(format!("search {}: {}", query, e).into()),
+                },
+                400 => return Err(
+                    "HTTP Status: 400 - Request parameters or body have missing or invalid fields"
+                        .into(),
+                ),
+                401 => return Err("HTTP Status: 401 - failed authentication!".into()),
+                403 => return Err("HTTP Code: 403 . Permission denied".into()),
+                404 => return Err("HTTP Code: 404 . Resource does not exist.".into()),
+                406 => {
+                    return Err(
+                        "HTTP Code: 406. Accept header does not include application/json".into(),
+                    )
+                }
+                _ => {
+                    println!("HTTP Status code: {}", k.status);
+                    println!("Body returned: {:#?}", k.body);
+                    return Err(format!("HTTP Status: {}", k.status).into());
+                }
+            };
+
+        total = page["total"].as_u64().unwrap_or_default() as usize;
+        let page_rows = match page["rows"].as_array_mut() {
+            Some(page_rows) => std::mem::take(page_rows),
+            None => Vec::new(),
+        };
+
+        if page_rows.is_empty() {
+            break;
+        }
+        rows.extend(page_rows);
+        if rows.len() >= total {
+            break;
+        }
+    }
+
+    if rows.len() < total {
+        return Err(format!(
+            "search {}: server returned {} of {} rows",
+            query,
+            rows.len(),
+            total
+        )
+        .into());
     }
+
+    Ok(rows)
+}
+
+/// partial_search - Runs a partial search on `index`, returning the `data` of every row with the
+/// keys requested in `keys`, e.g. `{"cookbooks": ["cookbooks"]}`. Every page of results is read.
+pub async fn partial_search(
+    config: &KnifeConfig,
+    index: &str,
+    query: &str,
+    keys: &Value,
+) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+    let mut rows = search_rows(config, index, query, keys).await?;
+    Ok(rows.iter_mut().map(|r| r["data"].take()).collect())
 }
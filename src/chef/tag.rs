@@ -0,0 +1,79 @@
+use crate::chef::node::{edit_nodes, get_node_json, NodeTarget};
+use crate::chef::run_list::display_diff;
+use crate::config::KnifeConfig;
+use serde_json::{json, Value};
+use std::error::Error;
+
+/// node_tags - Reads the tags of a node, they are stored in `normal.tags`
+fn node_tags(node: &Value) -> Vec<String> {
+    node["normal"]["tags"]
+        .as_array()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|t| t.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// list - Lists the tags of a node
+pub async fn list(config: &KnifeConfig, node_id: &str) -> Result<(), Box<dyn Error>> {
+    match get_node_json(config, node_id).await {
+        Ok(node) => {
+            for tag in node_tags(&node) {
+                println!("{}", tag);
+            }
+            Ok(())
+        }
+        Err(e) => Err(format!("tag list: {e}").into()),
+    }
+}
+
+/// edit - Adds or deletes tags on the targeted nodes
+pub async fn edit(
+    config: &KnifeConfig,
+    target: &NodeTarget,
+    tags: &[String],
+    add: bool,
+    yes: bool,
+) -> Result<(), Box<dyn Error>> {
+    if tags.is_empty() {
+        return Err("tag: no tags given".into());
+    }
+
+    let result = edit_nodes(config, target, yes, |name, node| {
+        let before = node_tags(node);
+        let after: Vec<String> = if add {
+            let mut after = before.clone();
+            for tag in tags {
+                if !after.contains(tag) {
+                    after.push(tag.clone());
+                }
+            }
+            after
+        } else {
+            before
+                .iter()
+                .filter(|t| !tags.contains(t))
+                .cloned()
+                .collect()
+        };
+
+        if after == before {
+            return Ok(false);
+        }
+
+        display_diff(&format!("node[{}] tags", name), &before, &after);
+        if !node["normal"].is_object() {
+            node["normal"] = json!({});
+        }
+        node["normal"]["tags"] = json!(after);
+        Ok(true)
+    })
+    .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(format!("tag: {e}").into()),
+    }
+}
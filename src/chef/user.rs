@@ -0,0 +1,39 @@
+use crate::{client, config::KnifeConfig, parse::traverse_json};
+use serde_json::Value;
+use std::error::Error;
+
+/// list - Lists the users of the Chef Server
+pub async fn list(config: &KnifeConfig) -> Result<(), Box<dyn Error>> {
+    match client::request::get(config, "/users", "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let users: Value = serde_json::from_str(&r.body)?;
+                for (k, _) in users.as_object().unwrap() {
+                    println!("{k}");
+                }
+                Ok(())
+            }
+            403 => Err("user list: Permission denied, listing users needs a server admin".into()),
+            _ => Err(format!("user list: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("user list: {e}").into()),
+    }
+}
+
+/// show - Shows a Chef Server user
+pub async fn show(config: &KnifeConfig, user: &str) -> Result<(), Box<dyn Error>> {
+    let request_path = format!("/users/{}", user);
+
+    match client::request::get(config, &request_path, "").await {
+        Ok(r) => match r.status {
+            200 => {
+                let chef_user: Value = serde_json::from_str(&r.body)?;
+                traverse_json(&chef_user, "");
+                Ok(())
+            }
+            404 => Err(format!("User not found: {}", user).into()),
+            _ => Err(format!("user show: HTTP Status: {}", r.status).into()),
+        },
+        Err(e) => Err(format!("user show: {e}").into()),
+    }
+}
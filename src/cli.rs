@@ -1,3 +1,8 @@
+use crate::chef::acl::{AclMemberType, AclObjectType, AclPermission};
+use crate::chef::graph::GraphFormat;
+use crate::chef::group::GroupMemberType;
+use crate::chef::key::KeyOwner;
+use crate::chef::node::AttributeLevel;
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
@@ -19,6 +24,26 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
+    Acl {
+        #[command(subcommand)]
+        command: AclCommands,
+    },
+
+    Client {
+        #[command(subcommand)]
+        command: ClientCommands,
+    },
+
+    Container {
+        #[command(subcommand)]
+        command: ContainerCommands,
+    },
+
+    Cookbook {
+        #[command(subcommand)]
+        command: CookbookCommands,
+    },
+
     Data {
         #[command(subcommand)]
         command: DataCommands,
@@ -28,11 +53,36 @@ pub enum Commands {
         #[command(subcommand)]
         command: EnvironmentCommands,
     },
+    Graph {
+        #[command(subcommand)]
+        command: GraphCommands,
+    },
+
+    Group {
+        #[command(subcommand)]
+        command: GroupCommands,
+    },
+
+    Key {
+        #[command(subcommand)]
+        command: KeyCommands,
+    },
+
     Node {
         #[command(subcommand)]
         command: NodeCommands,
     },
 
+    Org {
+        #[command(subcommand)]
+        command: OrgCommands,
+    },
+
+    Policy {
+        #[command(subcommand)]
+        command: PolicyCommands,
+    },
+
     Role {
         #[command(subcommand)]
         command: RoleCommands,
@@ -52,6 +102,220 @@ pub enum Commands {
         #[arg(short = 'x', long = "ssh-user")]
         user: Option<String>,
     },
+
+    Tag {
+        #[command(subcommand)]
+        command: TagCommands,
+    },
+
+    User {
+        #[command(subcommand)]
+        command: UserCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AclCommands {
+    /// Show the ACL of an object
+    Show {
+        #[arg(value_enum)]
+        object_type: AclObjectType,
+        name: String,
+    },
+
+    /// Add an actor or group to a permission of an object
+    Add {
+        #[arg(value_enum)]
+        object_type: AclObjectType,
+        name: String,
+        #[arg(value_enum)]
+        permission: AclPermission,
+        #[arg(value_enum)]
+        member_type: AclMemberType,
+        member: String,
+    },
+
+    /// Remove an actor or group from a permission of an object
+    Remove {
+        #[arg(value_enum)]
+        object_type: AclObjectType,
+        name: String,
+        #[arg(value_enum)]
+        permission: AclPermission,
+        #[arg(value_enum)]
+        member_type: AclMemberType,
+        member: String,
+    },
+
+    /// Change the ACL of every object whose name matches a regex
+    Bulk {
+        #[command(subcommand)]
+        command: AclBulkCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AclBulkCommands {
+    /// Add an actor or group to a permission of the matching objects
+    Add {
+        #[arg(value_enum)]
+        object_type: AclObjectType,
+        pattern: String,
+        #[arg(value_enum)]
+        permission: AclPermission,
+        #[arg(value_enum)]
+        member_type: AclMemberType,
+        member: String,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Remove an actor or group from a permission of the matching objects
+    Remove {
+        #[arg(value_enum)]
+        object_type: AclObjectType,
+        pattern: String,
+        #[arg(value_enum)]
+        permission: AclPermission,
+        #[arg(value_enum)]
+        member_type: AclMemberType,
+        member: String,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClientCommands {
+    /// List API clients
+    List,
+
+    /// Show API client
+    Show { client_id: String },
+
+    /// Create an API client
+    Create {
+        client_id: String,
+
+        /// Create a validator client
+        #[arg(long)]
+        validator: bool,
+
+        /// Generate the RSA key locally and only send the public key
+        #[arg(long)]
+        local_key: bool,
+
+        /// Write the private key to this file instead of printing it
+        #[arg(short = 'f', long)]
+        key_out: Option<String>,
+    },
+
+    /// Delete an API client
+    Delete {
+        client_id: String,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Regenerate the API client key
+    Reregister {
+        client_id: String,
+
+        /// Write the private key to this file instead of printing it
+        #[arg(short = 'f', long)]
+        key_out: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ContainerCommands {
+    /// List containers
+    List,
+
+    /// Show container
+    Show { container_id: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CookbookCommands {
+    /// List cookbooks with their latest version
+    List {
+        /// Show every version of each cookbook
+        #[arg(short = 'a', long)]
+        all_versions: bool,
+    },
+
+    /// Show cookbook versions, or the files of a cookbook version
+    Show {
+        cookbook_id: String,
+
+        version: Option<String>,
+
+        /// Only show one part: recipes, attributes, definitions, files, libraries, providers,
+        /// resources, templates or root_files
+        part: Option<String>,
+    },
+
+    /// Show cookbook version metadata
+    Metadata {
+        cookbook_id: String,
+        version: String,
+    },
+
+    /// Download a cookbook version to a local directory
+    Download {
+        cookbook_id: String,
+
+        version: Option<String>,
+
+        /// Download the latest version
+        #[arg(short = 'N', long, conflicts_with = "version")]
+        latest: bool,
+
+        /// Directory the NAME-VERSION cookbook directory is created in
+        #[arg(short = 'd', long, default_value = ".")]
+        dir: String,
+
+        /// Overwrite an existing download
+        #[arg(short = 'f', long)]
+        force: bool,
+    },
+
+    /// Upload a cookbook directory
+    Upload {
+        path: String,
+
+        /// Freeze the uploaded version so it can't be overwritten
+        #[arg(long)]
+        freeze: bool,
+
+        /// Overwrite a frozen version
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Show which versions environments allow and nodes ran
+    Usage { cookbook_id: String },
+
+    /// Delete cookbook versions no environment, node or kept cookbook version uses
+    Prune {
+        /// Only prune this cookbook
+        cookbook_id: Option<String>,
+
+        /// Always keep this many of the newest versions
+        #[arg(short = 'k', long, default_value_t = 1)]
+        keep: usize,
+
+        /// Only list the versions that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -62,13 +326,134 @@ pub enum DataCommands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum GraphCommands {
+    /// Graph role -> role and role -> recipe -> cookbook relationships
+    Roles {
+        /// Only follow the run lists applied in this environment
+        #[arg(short = 'E', long)]
+        env: Option<String>,
+
+        #[arg(short = 'f', long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+
+        /// Add the number of nodes using each role
+        #[arg(long)]
+        with_nodes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GroupCommands {
+    /// List groups
+    List,
+
+    /// Show group members
+    Show {
+        group_id: String,
+
+        /// Expand nested groups to their effective users and clients
+        #[arg(short = 'r', long)]
+        recursive: bool,
+    },
+
+    /// Create a group
+    Create { group_id: String },
+
+    /// Delete a group
+    Delete {
+        group_id: String,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Add a user (actor), client or group to a group
+    Add {
+        #[arg(value_enum)]
+        member_type: GroupMemberType,
+        member: String,
+        group_id: String,
+    },
+
+    /// Remove a user (actor), client or group from a group
+    Remove {
+        #[arg(value_enum)]
+        member_type: GroupMemberType,
+        member: String,
+        group_id: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeyCommands {
+    /// List the keys of a client or user
+    List {
+        #[arg(value_enum)]
+        owner: KeyOwner,
+        name: String,
+    },
+
+    /// Show a key
+    Show {
+        #[arg(value_enum)]
+        owner: KeyOwner,
+        name: String,
+        key_name: String,
+    },
+
+    /// Generate a key locally and upload its public key
+    Create {
+        #[arg(value_enum)]
+        owner: KeyOwner,
+        name: String,
+
+        /// Key name, defaults to key-TIMESTAMP
+        #[arg(short = 'k', long)]
+        key_name: Option<String>,
+
+        /// Expire the key after this many days, by default it never expires
+        #[arg(short = 'e', long)]
+        expires_in_days: Option<i64>,
+
+        /// File the private key is written to
+        #[arg(short = 'f', long)]
+        key_out: String,
+    },
+
+    /// Delete a key
+    Delete {
+        #[arg(value_enum)]
+        owner: KeyOwner,
+        name: String,
+        key_name: String,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum NodeCommands {
     /// List all the nodes from Chef Server
     List,
 
     /// Show node information
-    Show { node_id: String },
+    Show {
+        node_id: String,
+
+        /// Only show these dotted attribute paths, e.g. kernel.release
+        #[arg(short = 'a', long = "attribute")]
+        attributes: Vec<String>,
+
+        /// Attribute precedence level to read, merged when not set
+        #[arg(short = 'l', long, value_enum)]
+        level: Option<AttributeLevel>,
+
+        /// Show every attribute
+        #[arg(short = 'L', long)]
+        long: bool,
+    },
 
     /// SSH into the node
     Ssh {
@@ -76,6 +461,221 @@ pub enum NodeCommands {
         #[arg(short = 'x', long = "ssh-user")]
         user: Option<String>,
     },
+
+    /// Create a node
+    Create {
+        node_id: String,
+
+        #[arg(short = 'E', long, default_value = "_default")]
+        env: String,
+
+        #[arg(short = 'r', long = "run-list", num_args = 1..)]
+        run_list: Vec<String>,
+    },
+
+    /// Create or update nodes from JSON files
+    From {
+        #[command(subcommand)]
+        command: NodeFromCommands,
+    },
+
+    /// Create a node with the run list, environment and normal attributes of another node
+    Clone {
+        source_id: String,
+        destination_id: String,
+    },
+
+    /// Compare the attributes of two nodes
+    Diff {
+        node_a: String,
+        node_b: String,
+
+        /// Attribute precedence level to compare
+        #[arg(short = 'l', long, value_enum, default_value = "merged")]
+        level: AttributeLevel,
+
+        /// Only compare attributes below this dotted path
+        #[arg(short = 'p', long)]
+        path: Option<String>,
+
+        /// Include attributes that always differ, like ohai_time and uptime
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Delete nodes, or every node matching --query
+    Delete {
+        #[arg(required_unless_present = "query")]
+        nodes: Vec<String>,
+
+        /// Delete every node matching this search query
+        #[arg(short = 'q', long, conflicts_with = "nodes")]
+        query: Option<String>,
+
+        /// Also delete the API client with the same name
+        #[arg(long)]
+        with_client: bool,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Expand the node run list through nested roles
+    Expand { node_id: String },
+
+    /// Change normal attributes of a node, or of every node matching --query
+    Attribute {
+        #[command(subcommand)]
+        command: NodeAttributeCommands,
+    },
+
+    /// Manage the environment of a node, or of every node matching --query
+    Environment {
+        #[command(subcommand)]
+        command: NodeEnvironmentCommands,
+    },
+
+    /// Explain which precedence levels, roles and environment set an attribute
+    Explain {
+        node_id: String,
+
+        /// Dotted attribute path
+        path: String,
+    },
+
+    /// Manage the run list of a node, or of every node matching --query
+    #[command(name = "run_list")]
+    RunList {
+        #[command(subcommand)]
+        command: NodeRunListCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NodeFromCommands {
+    /// Upload nodes from node.json files
+    File {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NodeAttributeCommands {
+    /// Set a normal attribute, e.g. `nginx.worker_processes 4`
+    Set {
+        /// Node name, left out with --query
+        node_id: Option<String>,
+
+        /// Dotted attribute path
+        path: Option<String>,
+
+        value: Option<String>,
+
+        /// Parse the value as JSON instead of storing it as a string
+        #[arg(long)]
+        json: bool,
+
+        /// Change every node matching this search query
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Delete a normal attribute
+    Delete {
+        /// Node name, left out with --query
+        node_id: Option<String>,
+
+        /// Dotted attribute path
+        path: Option<String>,
+
+        /// Change every node matching this search query
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NodeEnvironmentCommands {
+    /// Move nodes to an environment
+    Set {
+        /// Node name, left out with --query
+        node_id: Option<String>,
+
+        /// Environment to move to, the only positional argument with --query
+        #[arg(conflicts_with = "query")]
+        environment_id: Option<String>,
+
+        /// Change every node matching this search query
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NodeRunListCommands {
+    /// Add items to the run list
+    Add {
+        /// Node name, left out with --query
+        node_id: Option<String>,
+
+        items: Vec<String>,
+
+        /// Insert the items before this run list item
+        #[arg(long, conflicts_with = "after")]
+        before: Option<String>,
+
+        /// Insert the items after this run list item
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Change every node matching this search query
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Remove items from the run list
+    Remove {
+        /// Node name, left out with --query
+        node_id: Option<String>,
+
+        items: Vec<String>,
+
+        /// Change every node matching this search query
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Replace the run list
+    Set {
+        /// Node name, left out with --query
+        node_id: Option<String>,
+
+        /// New run list, clearing it with no items requires --yes
+        items: Vec<String>,
+
+        /// Change every node matching this search query
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -87,6 +687,99 @@ pub enum EnvironmentCommands {
     Show { environment_id: String },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum OrgCommands {
+    /// Manage organization members
+    User {
+        #[command(subcommand)]
+        command: OrgUserCommands,
+    },
+
+    /// Manage invites to the organization
+    Invite {
+        #[command(subcommand)]
+        command: OrgInviteCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OrgUserCommands {
+    /// List organization members
+    List,
+
+    /// Add a user to the organization, needs a server admin
+    Add { user_id: String },
+
+    /// Remove a user from the organization
+    Remove {
+        user_id: String,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OrgInviteCommands {
+    /// List pending invites
+    List,
+
+    /// Invite a user to the organization
+    Add { user_id: String },
+
+    /// Cancel a pending invite
+    Cancel { user_id: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PolicyCommands {
+    /// List policies
+    List,
+
+    /// Show the revisions of a policy, or the cookbooks locked by one revision
+    Show {
+        policy_id: String,
+
+        revision: Option<String>,
+    },
+
+    /// Compare the revisions and cookbook locks of two policy groups
+    Diff {
+        group_a: String,
+        group_b: String,
+
+        /// Only compare this policy
+        policy_id: Option<String>,
+    },
+
+    /// Pin a policy revision in a policy group
+    Promote {
+        policy_id: String,
+        revision: String,
+
+        #[arg(long = "to")]
+        group_id: String,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Inspect policy groups
+    Group {
+        #[command(subcommand)]
+        command: PolicyGroupCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PolicyGroupCommands {
+    /// List policy groups with their pinned revisions
+    List,
+
+    /// Show the revisions and locked cookbooks of a policy group
+    Show { group_id: String },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum RoleCommands {
     /// List roles
@@ -94,6 +787,150 @@ pub enum RoleCommands {
 
     /// Show role
     Show { role_id: String },
+
+    /// Create a role
+    Create {
+        role_id: String,
+
+        #[arg(short = 'd', long, default_value = "")]
+        description: String,
+
+        #[arg(short = 'r', long = "run-list", num_args = 1..)]
+        run_list: Vec<String>,
+    },
+
+    /// Edit a role in $EDITOR
+    Edit { role_id: String },
+
+    /// Delete a role
+    Delete {
+        role_id: String,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Upload roles from JSON or Ruby DSL files
+    From {
+        #[command(subcommand)]
+        command: RoleFromCommands,
+    },
+
+    /// Expand the role run list through nested roles
+    Expand {
+        role_id: String,
+
+        #[arg(short = 'E', long, default_value = "_default")]
+        env: String,
+    },
+
+    /// Show which nodes use a role, or a usage summary of all roles
+    Usage { role_id: Option<String> },
+
+    /// Manage the role run list or one of its environment run lists
+    #[command(name = "run_list")]
+    RunList {
+        #[command(subcommand)]
+        command: RoleRunListCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RoleFromCommands {
+    /// Upload roles from roles/*.json or roles/*.rb files
+    File {
+        #[arg(required = true)]
+        files: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RoleRunListCommands {
+    /// Add items to the run list
+    Add {
+        role_id: String,
+
+        #[arg(required = true)]
+        items: Vec<String>,
+
+        /// Insert the items after this run list item
+        #[arg(long)]
+        after: Option<String>,
+
+        /// Change the env_run_lists entry for this environment
+        #[arg(short = 'E', long)]
+        env: Option<String>,
+    },
+
+    /// Remove items from the run list
+    Remove {
+        role_id: String,
+
+        #[arg(required = true)]
+        items: Vec<String>,
+
+        #[arg(short = 'E', long)]
+        env: Option<String>,
+    },
+
+    /// Replace the run list
+    Set {
+        role_id: String,
+
+        /// New run list, clearing it with no items requires --yes
+        items: Vec<String>,
+
+        #[arg(short = 'E', long)]
+        env: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TagCommands {
+    /// List the tags of a node
+    List { node_id: String },
+
+    /// Add tags to a node, or to every node matching --query
+    Add {
+        /// Node name, left out with --query
+        node_id: Option<String>,
+
+        tags: Vec<String>,
+
+        /// Change every node matching this search query
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Delete tags from a node, or from every node matching --query
+    Delete {
+        /// Node name, left out with --query
+        node_id: Option<String>,
+
+        tags: Vec<String>,
+
+        /// Change every node matching this search query
+        #[arg(short = 'q', long)]
+        query: Option<String>,
+
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum UserCommands {
+    /// List Chef Server users
+    List,
+
+    /// Show user
+    Show { user_id: String },
 }
 
 #[derive(Subcommand, Debug)]
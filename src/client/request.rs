@@ -1,6 +1,7 @@
 use crate::config::KnifeConfig;
 use reqwest;
-use serde_json::json;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::error::Error;
 
@@ -13,6 +14,9 @@ pub struct ChefServerResponse {
     pub body: String,
 }
 
+/// Rows requested per search page, Chef Server returns at most this many rows at once
+pub const SEARCH_ROWS: usize = 1000;
+
 /// get - issues a GET request to Chef Server API returning the `ChefServerResponse` struct
 pub async fn get(
     config: &KnifeConfig,
@@ -56,42 +60,57 @@ pub async fn get(
     Ok(resp)
 }
 
-/// get - issues a GET request to Chef Server API returning the `ChefServerResponse` struct
+/// get_params - issues a GET request with arbitrary query parameters, e.g. `num_versions=all`
+pub async fn get_params(
+    config: &KnifeConfig,
+    request_path: &str,
+    params: &[(&str, &str)],
+) -> Result<ChefServerResponse, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::ClientBuilder::new()
+        .http1_title_case_headers()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let base_url = url::Url::parse(&config.chef_server_url)?;
+    let full_url = base_url.join(request_path)?;
+    let headers = request_headers(config, request_path, "GET", None)?;
+
+    let response = client
+        .get(full_url)
+        .query(params)
+        .headers(headers)
+        .send()
+        .await?;
+
+    let status = response.status().as_u16();
+    let body = response.text().await?;
+
+    Ok(ChefServerResponse { status, body })
+}
+
+/// post - issues a partial search POST request to Chef Server API returning the
+/// `ChefServerResponse` struct. `request_body` maps the returned keys to attribute paths.
 pub async fn post(
     config: &KnifeConfig,
     request_path: &str,
     query: &str,
+    start: usize,
+    request_body: &Value,
 ) -> Result<ChefServerResponse, Box<dyn Error + Send + Sync>> {
     let client = reqwest::ClientBuilder::new()
         .http1_title_case_headers()
         .danger_accept_invalid_certs(true)
         .build()?;
 
-    let request_body = json!({
-
-        "node_name": ["name"],
-        "chef_environment": ["chef_environment"],
-        "hostname": ["hostname"],
-        "ipaddress": ["ipaddress"],
-        "platform_family": ["platform_family"],
-        "platform_version": ["platform_version"],
-        "roles": ["roles"],
-        "run_list": ["run_list"],
-        "macaddress": ["macaddress"],
-        "os": ["os"],
-        "os_version": ["os_version"],
-        "fqdn": ["fqdn"],
-        "platform": ["platform_version"],
-        "recipes": ["recipes"]
-    });
-
-    let body = serde_json::to_string(&request_body)?;
+    let body = serde_json::to_string(request_body)?;
 
+    let start = start.to_string();
+    let rows = SEARCH_ROWS.to_string();
     let mut query_params = HashMap::new();
     if !query.is_empty() {
         query_params.insert("q", query);
-        query_params.insert("start", "0");
-        query_params.insert("rows", "1000");
+        query_params.insert("start", &start);
+        query_params.insert("rows", &rows);
     }
 
     let base_url = url::Url::parse(&config.chef_server_url)?;
@@ -119,3 +138,136 @@ pub async fn post(
 
     Ok(resp)
 }
+
+/// put - issues a PUT request with a JSON body to Chef Server API returning the `ChefServerResponse` struct
+pub async fn put(
+    config: &KnifeConfig,
+    request_path: &str,
+    body: &str,
+) -> Result<ChefServerResponse, Box<dyn Error + Send + Sync>> {
+    put_params(config, request_path, body, &[]).await
+}
+
+/// put_params - issues a PUT request with a JSON body and query parameters, e.g. `force=true`
+pub async fn put_params(
+    config: &KnifeConfig,
+    request_path: &str,
+    body: &str,
+    params: &[(&str, &str)],
+) -> Result<ChefServerResponse, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::ClientBuilder::new()
+        .http1_title_case_headers()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let base_url = url::Url::parse(&config.chef_server_url)?;
+    let full_url = base_url.join(request_path)?;
+    let headers = request_headers(config, request_path, "PUT", Some(body.to_string()))?;
+
+    let response = client
+        .put(full_url)
+        .query(params)
+        .headers(headers)
+        .body(body.to_string())
+        .send()
+        .await?;
+
+    let status = response.status().as_u16();
+    let body = response.text().await?;
+
+    Ok(ChefServerResponse { status, body })
+}
+
+/// post_body - issues a POST request with a JSON body to Chef Server API returning the
+/// `ChefServerResponse` struct. Used for object creation, unlike `post` which is tied to search.
+pub async fn post_body(
+    config: &KnifeConfig,
+    request_path: &str,
+    body: &str,
+) -> Result<ChefServerResponse, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::ClientBuilder::new()
+        .http1_title_case_headers()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let base_url = url::Url::parse(&config.chef_server_url)?;
+    let full_url = base_url.join(request_path)?;
+    let headers = request_headers(config, request_path, "POST", Some(body.to_string()))?;
+
+    let response = client
+        .post(full_url)
+        .headers(headers)
+        .body(body.to_string())
+        .send()
+        .await?;
+
+    let status = response.status().as_u16();
+    let body = response.text().await?;
+
+    Ok(ChefServerResponse { status, body })
+}
+
+/// delete - issues a DELETE request to Chef Server API returning the `ChefServerResponse` struct
+pub async fn delete(
+    config: &KnifeConfig,
+    request_path: &str,
+) -> Result<ChefServerResponse, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::ClientBuilder::new()
+        .http1_title_case_headers()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let base_url = url::Url::parse(&config.chef_server_url)?;
+    let full_url = base_url.join(request_path)?;
+    let headers = request_headers(config, request_path, "DELETE", None)?;
+
+    let response = client.delete(full_url).headers(headers).send().await?;
+
+    let status = response.status().as_u16();
+    let body = response.text().await?;
+
+    Ok(ChefServerResponse { status, body })
+}
+
+/// get_file - downloads a file from a pre-signed cookbook file URL. Those URLs carry their own
+/// signature, so no Chef authentication headers are sent.
+pub async fn get_file(url: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let client = reqwest::ClientBuilder::new()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let response = client.get(url).send().await?;
+    let status = response.status().as_u16();
+    if status != 200 {
+        return Err(format!("HTTP Status: {}", status).into());
+    }
+
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// put_file - uploads a cookbook file to the pre-signed sandbox URL returned by Chef Server.
+/// `content_md5` is the base64 encoded MD5 digest the URL was signed for.
+pub async fn put_file(
+    url: &str,
+    content: Vec<u8>,
+    content_md5: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let client = reqwest::ClientBuilder::new()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    let response = client
+        .put(url)
+        .header(CONTENT_TYPE, "application/x-binary")
+        .header("Content-MD5", content_md5)
+        .header(ACCEPT, "application/json")
+        .body(content)
+        .send()
+        .await?;
+
+    let status = response.status().as_u16();
+    match status {
+        200..=299 => Ok(()),
+        _ => Err(format!("HTTP Status: {}: {}", status, response.text().await?).into()),
+    }
+}
@@ -2,3 +2,5 @@ pub mod chef;
 pub mod client;
 pub mod config;
 pub mod parse;
+pub mod prompt;
+pub mod ruby;
@@ -7,6 +7,8 @@ mod cli;
 mod client;
 mod config;
 mod parse;
+mod prompt;
+mod ruby;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -15,6 +17,199 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let config = config::KnifeConfig::from_file(&cli_options.config, &cli_options.profile)?;
 
     match cli_options.command {
+        cli::Commands::Acl { command } => match command {
+            cli::AclCommands::Show { object_type, name } => {
+                chef::acl::show(&config, object_type, &name).await?;
+            }
+
+            cli::AclCommands::Add {
+                object_type,
+                name,
+                permission,
+                member_type,
+                member,
+            } => {
+                chef::acl::edit(
+                    &config,
+                    object_type,
+                    &name,
+                    chef::acl::AclChange::Add,
+                    permission,
+                    member_type,
+                    &member,
+                )
+                .await?;
+            }
+
+            cli::AclCommands::Remove {
+                object_type,
+                name,
+                permission,
+                member_type,
+                member,
+            } => {
+                chef::acl::edit(
+                    &config,
+                    object_type,
+                    &name,
+                    chef::acl::AclChange::Remove,
+                    permission,
+                    member_type,
+                    &member,
+                )
+                .await?;
+            }
+
+            cli::AclCommands::Bulk { command } => match command {
+                cli::AclBulkCommands::Add {
+                    object_type,
+                    pattern,
+                    permission,
+                    member_type,
+                    member,
+                    yes,
+                } => {
+                    chef::acl::bulk_edit(
+                        &config,
+                        object_type,
+                        &pattern,
+                        chef::acl::AclChange::Add,
+                        permission,
+                        member_type,
+                        &member,
+                        yes,
+                    )
+                    .await?;
+                }
+
+                cli::AclBulkCommands::Remove {
+                    object_type,
+                    pattern,
+                    permission,
+                    member_type,
+                    member,
+                    yes,
+                } => {
+                    chef::acl::bulk_edit(
+                        &config,
+                        object_type,
+                        &pattern,
+                        chef::acl::AclChange::Remove,
+                        permission,
+                        member_type,
+                        &member,
+                        yes,
+                    )
+                    .await?;
+                }
+            },
+        },
+
+        cli::Commands::Client { command } => match command {
+            cli::ClientCommands::List => {
+                chef::client::list(&config).await?;
+            }
+
+            cli::ClientCommands::Show { client_id } => {
+                chef::client::show(&config, &client_id).await?;
+            }
+
+            cli::ClientCommands::Create {
+                client_id,
+                validator,
+                local_key,
+                key_out,
+            } => {
+                chef::client::create(
+                    &config,
+                    &client_id,
+                    validator,
+                    local_key,
+                    key_out.as_deref(),
+                )
+                .await?;
+            }
+
+            cli::ClientCommands::Delete { client_id, yes } => {
+                chef::client::delete(&config, &client_id, yes).await?;
+            }
+
+            cli::ClientCommands::Reregister { client_id, key_out } => {
+                chef::client::reregister(&config, &client_id, key_out.as_deref()).await?;
+            }
+        },
+
+        cli::Commands::Container { command } => match command {
+            cli::ContainerCommands::List => {
+                chef::container::list(&config).await?;
+            }
+
+            cli::ContainerCommands::Show { container_id } => {
+                chef::container::show(&config, &container_id).await?;
+            }
+        },
+
+        cli::Commands::Cookbook { command } => match command {
+            cli::CookbookCommands::List { all_versions } => {
+                chef::cookbook::list(&config, all_versions).await?;
+            }
+
+            cli::CookbookCommands::Show {
+                cookbook_id,
+                version,
+                part,
+            } => {
+                chef::cookbook::show(&config, &cookbook_id, version.as_deref(), part.as_deref())
+                    .await?;
+            }
+
+            cli::CookbookCommands::Metadata {
+                cookbook_id,
+                version,
+            } => {
+                chef::cookbook::metadata(&config, &cookbook_id, &version).await?;
+            }
+
+            cli::CookbookCommands::Download {
+                cookbook_id,
+                version,
+                latest,
+                dir,
+                force,
+            } => {
+                chef::cookbook::download(
+                    &config,
+                    &cookbook_id,
+                    version.as_deref(),
+                    latest,
+                    &dir,
+                    force,
+                )
+                .await?;
+            }
+
+            cli::CookbookCommands::Upload {
+                path,
+                freeze,
+                force,
+            } => {
+                chef::cookbook::upload(&config, &path, freeze, force).await?;
+            }
+
+            cli::CookbookCommands::Usage { cookbook_id } => {
+                chef::cookbook::usage(&config, &cookbook_id).await?;
+            }
+
+            cli::CookbookCommands::Prune {
+                cookbook_id,
+                keep,
+                dry_run,
+                yes,
+            } => {
+                chef::cookbook::prune(&config, cookbook_id.as_deref(), keep, dry_run, yes).await?;
+            }
+        },
+
         cli::Commands::Data { command } => match command {
             cli::DataCommands::Bag { command } => match command {
                 cli::DataBagCommands::List => {
@@ -40,16 +235,329 @@ async fn main() -> Result<(), Box<dyn Error>> {
             }
         },
 
+        cli::Commands::Graph { command } => match command {
+            cli::GraphCommands::Roles {
+                env,
+                format,
+                with_nodes,
+            } => {
+                chef::graph::roles(&config, env.as_deref(), format, with_nodes).await?;
+            }
+        },
+
+        cli::Commands::Group { command } => match command {
+            cli::GroupCommands::List => {
+                chef::group::list(&config).await?;
+            }
+
+            cli::GroupCommands::Show {
+                group_id,
+                recursive,
+            } => {
+                chef::group::show(&config, &group_id, recursive).await?;
+            }
+
+            cli::GroupCommands::Create { group_id } => {
+                chef::group::create(&config, &group_id).await?;
+            }
+
+            cli::GroupCommands::Delete { group_id, yes } => {
+                chef::group::delete(&config, &group_id, yes).await?;
+            }
+
+            cli::GroupCommands::Add {
+                member_type,
+                member,
+                group_id,
+            } => {
+                chef::group::edit_members(&config, &group_id, member_type, &member, true).await?;
+            }
+
+            cli::GroupCommands::Remove {
+                member_type,
+                member,
+                group_id,
+            } => {
+                chef::group::edit_members(&config, &group_id, member_type, &member, false).await?;
+            }
+        },
+
+        cli::Commands::Key { command } => match command {
+            cli::KeyCommands::List { owner, name } => {
+                chef::key::list(&config, owner, &name).await?;
+            }
+
+            cli::KeyCommands::Show {
+                owner,
+                name,
+                key_name,
+            } => {
+                chef::key::show(&config, owner, &name, &key_name).await?;
+            }
+
+            cli::KeyCommands::Create {
+                owner,
+                name,
+                key_name,
+                expires_in_days,
+                key_out,
+            } => {
+                chef::key::create(
+                    &config,
+                    owner,
+                    &name,
+                    key_name.as_deref(),
+                    expires_in_days,
+                    &key_out,
+                )
+                .await?;
+            }
+
+            cli::KeyCommands::Delete {
+                owner,
+                name,
+                key_name,
+                yes,
+            } => {
+                chef::key::delete(&config, owner, &name, &key_name, yes).await?;
+            }
+        },
+
         cli::Commands::Node { command } => match command {
             cli::NodeCommands::List => {
                 chef::node::node_list(&config).await?;
             }
-            cli::NodeCommands::Show { node_id } => {
-                chef::node::node_show(&config, &node_id).await?;
+            cli::NodeCommands::Show {
+                node_id,
+                attributes,
+                level,
+                long,
+            } => {
+                chef::node::node_show(&config, &node_id, &attributes, level, long).await?;
             }
             cli::NodeCommands::Ssh { node_id, user } => {
                 chef::node::node_ssh(&config, &node_id, user).await?;
             }
+            cli::NodeCommands::Expand { node_id } => {
+                chef::node::node_expand(&config, &node_id).await?;
+            }
+            cli::NodeCommands::Attribute { command } => match command {
+                cli::NodeAttributeCommands::Set {
+                    node_id,
+                    path,
+                    value,
+                    json,
+                    query,
+                    yes,
+                } => {
+                    let (target, args) = chef::node::NodeTarget::from_args(
+                        node_id,
+                        query,
+                        path.into_iter().chain(value).collect(),
+                    )?;
+                    let [path, value] = args.as_slice() else {
+                        return Err("node attribute set: a path and a value are required".into());
+                    };
+                    let value = if json {
+                        serde_json::from_str(value)?
+                    } else {
+                        serde_json::Value::String(value.clone())
+                    };
+                    chef::node::attribute_edit(&config, &target, path, Some(&value), yes).await?;
+                }
+
+                cli::NodeAttributeCommands::Delete {
+                    node_id,
+                    path,
+                    query,
+                    yes,
+                } => {
+                    let (target, args) = chef::node::NodeTarget::from_args(
+                        node_id,
+                        query,
+                        path.into_iter().collect(),
+                    )?;
+                    let Some(path) = args.first() else {
+                        return Err("node attribute delete: a path is required".into());
+                    };
+                    chef::node::attribute_edit(&config, &target, path, None, yes).await?;
+                }
+            },
+
+            cli::NodeCommands::Environment { command } => match command {
+                cli::NodeEnvironmentCommands::Set {
+                    node_id,
+                    environment_id,
+                    query,
+                    yes,
+                } => {
+                    let (target, args) = chef::node::NodeTarget::from_args(
+                        node_id,
+                        query,
+                        environment_id.into_iter().collect(),
+                    )?;
+                    let Some(environment_id) = args.first() else {
+                        return Err("node environment set: an environment is required".into());
+                    };
+                    chef::node::environment_set(&config, &target, environment_id, yes).await?;
+                }
+            },
+
+            cli::NodeCommands::Create {
+                node_id,
+                env,
+                run_list,
+            } => {
+                chef::node::node_create(&config, &node_id, &env, &run_list).await?;
+            }
+            cli::NodeCommands::From { command } => match command {
+                cli::NodeFromCommands::File { files } => {
+                    chef::node::node_from_file(&config, &files).await?;
+                }
+            },
+            cli::NodeCommands::Clone {
+                source_id,
+                destination_id,
+            } => {
+                chef::node::node_clone(&config, &source_id, &destination_id).await?;
+            }
+            cli::NodeCommands::Diff {
+                node_a,
+                node_b,
+                level,
+                path,
+                all,
+            } => {
+                chef::node::node_diff(&config, &node_a, &node_b, level, path.as_deref(), all)
+                    .await?;
+            }
+            cli::NodeCommands::Delete {
+                nodes,
+                query,
+                with_client,
+                yes,
+            } => {
+                let targets: Vec<chef::node::NodeTarget> = match query {
+                    Some(query) => vec![chef::node::NodeTarget::Query(query)],
+                    None => nodes
+                        .into_iter()
+                        .map(chef::node::NodeTarget::Node)
+                        .collect(),
+                };
+                chef::node::node_delete(&config, &targets, with_client, yes).await?;
+            }
+
+            cli::NodeCommands::Explain { node_id, path } => {
+                chef::node::node_explain(&config, &node_id, &path).await?;
+            }
+
+            cli::NodeCommands::RunList { command } => match command {
+                cli::NodeRunListCommands::Add {
+                    node_id,
+                    items,
+                    before,
+                    after,
+                    query,
+                    yes,
+                } => {
+                    let (target, items) = chef::node::NodeTarget::from_args(node_id, query, items)?;
+                    let position = match (before, after) {
+                        (Some(b), _) => chef::run_list::Position::Before(b),
+                        (None, Some(a)) => chef::run_list::Position::After(a),
+                        (None, None) => chef::run_list::Position::End,
+                    };
+                    let edit = chef::run_list::RunListEdit::Add { items, position };
+                    chef::node::run_list_edit(&config, &target, &edit, yes).await?;
+                }
+
+                cli::NodeRunListCommands::Remove {
+                    node_id,
+                    items,
+                    query,
+                    yes,
+                } => {
+                    let (target, items) = chef::node::NodeTarget::from_args(node_id, query, items)?;
+                    let edit = chef::run_list::RunListEdit::Remove { items };
+                    chef::node::run_list_edit(&config, &target, &edit, yes).await?;
+                }
+
+                cli::NodeRunListCommands::Set {
+                    node_id,
+                    items,
+                    query,
+                    yes,
+                } => {
+                    let (target, items) = chef::node::NodeTarget::from_args(node_id, query, items)?;
+                    let edit = chef::run_list::RunListEdit::Set { items };
+                    chef::node::run_list_edit(&config, &target, &edit, yes).await?;
+                }
+            },
+        },
+
+        cli::Commands::Org { command } => match command {
+            cli::OrgCommands::User { command } => match command {
+                cli::OrgUserCommands::List => {
+                    chef::organization::user_list(&config).await?;
+                }
+                cli::OrgUserCommands::Add { user_id } => {
+                    chef::organization::user_add(&config, &user_id).await?;
+                }
+                cli::OrgUserCommands::Remove { user_id, yes } => {
+                    chef::organization::user_remove(&config, &user_id, yes).await?;
+                }
+            },
+
+            cli::OrgCommands::Invite { command } => match command {
+                cli::OrgInviteCommands::List => {
+                    chef::organization::invite_list(&config).await?;
+                }
+                cli::OrgInviteCommands::Add { user_id } => {
+                    chef::organization::invite_add(&config, &user_id).await?;
+                }
+                cli::OrgInviteCommands::Cancel { user_id } => {
+                    chef::organization::invite_cancel(&config, &user_id).await?;
+                }
+            },
+        },
+
+        cli::Commands::Policy { command } => match command {
+            cli::PolicyCommands::List => {
+                chef::policy::list(&config).await?;
+            }
+
+            cli::PolicyCommands::Show {
+                policy_id,
+                revision,
+            } => {
+                chef::policy::show(&config, &policy_id, revision.as_deref()).await?;
+            }
+
+            cli::PolicyCommands::Diff {
+                group_a,
+                group_b,
+                policy_id,
+            } => {
+                chef::policy::diff(&config, &group_a, &group_b, policy_id.as_deref()).await?;
+            }
+
+            cli::PolicyCommands::Promote {
+                policy_id,
+                revision,
+                group_id,
+                yes,
+            } => {
+                chef::policy::promote(&config, &policy_id, &revision, &group_id, yes).await?;
+            }
+
+            cli::PolicyCommands::Group { command } => match command {
+                cli::PolicyGroupCommands::List => {
+                    chef::policy::group_list(&config).await?;
+                }
+
+                cli::PolicyGroupCommands::Show { group_id } => {
+                    chef::policy::group_show(&config, &group_id).await?;
+                }
+            },
         },
 
         cli::Commands::Role { command } => match command {
@@ -60,6 +568,81 @@ async fn main() -> Result<(), Box<dyn Error>> {
             cli::RoleCommands::Show { role_id } => {
                 chef::role::show(&config, &role_id).await?;
             }
+
+            cli::RoleCommands::Create {
+                role_id,
+                description,
+                run_list,
+            } => {
+                chef::role::create(&config, &role_id, &description, &run_list).await?;
+            }
+
+            cli::RoleCommands::Edit { role_id } => {
+                chef::role::edit(&config, &role_id).await?;
+            }
+
+            cli::RoleCommands::Delete { role_id, yes } => {
+                chef::role::delete(&config, &role_id, yes).await?;
+            }
+
+            cli::RoleCommands::From { command } => match command {
+                cli::RoleFromCommands::File { files } => {
+                    chef::role::from_file(&config, &files).await?;
+                }
+            },
+
+            cli::RoleCommands::Expand { role_id, env } => {
+                chef::role::expand(&config, &role_id, &env).await?;
+            }
+
+            cli::RoleCommands::Usage { role_id } => {
+                chef::role::usage(&config, role_id.as_deref()).await?;
+            }
+
+            cli::RoleCommands::RunList { command } => {
+                let (role_id, edit, env, yes) = match command {
+                    cli::RoleRunListCommands::Add {
+                        role_id,
+                        items,
+                        after,
+                        env,
+                    } => {
+                        let position = match after {
+                            Some(a) => chef::run_list::Position::After(a),
+                            None => chef::run_list::Position::End,
+                        };
+                        (
+                            role_id,
+                            chef::run_list::RunListEdit::Add { items, position },
+                            env,
+                            false,
+                        )
+                    }
+                    cli::RoleRunListCommands::Remove {
+                        role_id,
+                        items,
+                        env,
+                    } => (
+                        role_id,
+                        chef::run_list::RunListEdit::Remove { items },
+                        env,
+                        false,
+                    ),
+                    cli::RoleRunListCommands::Set {
+                        role_id,
+                        items,
+                        env,
+                        yes,
+                    } => (
+                        role_id,
+                        chef::run_list::RunListEdit::Set { items },
+                        env,
+                        yes,
+                    ),
+                };
+
+                chef::role::edit_run_list(&config, &role_id, &edit, env.as_deref(), yes).await?;
+            }
         },
 
         cli::Commands::Search { query, attributes } => {
@@ -73,6 +656,42 @@ async fn main() -> Result<(), Box<dyn Error>> {
         } => {
             chef::ssh::ssh_nodes(&config, &query, &command, user).await?;
         }
+
+        cli::Commands::Tag { command } => match command {
+            cli::TagCommands::List { node_id } => {
+                chef::tag::list(&config, &node_id).await?;
+            }
+
+            cli::TagCommands::Add {
+                node_id,
+                tags,
+                query,
+                yes,
+            } => {
+                let (target, tags) = chef::node::NodeTarget::from_args(node_id, query, tags)?;
+                chef::tag::edit(&config, &target, &tags, true, yes).await?;
+            }
+
+            cli::TagCommands::Delete {
+                node_id,
+                tags,
+                query,
+                yes,
+            } => {
+                let (target, tags) = chef::node::NodeTarget::from_args(node_id, query, tags)?;
+                chef::tag::edit(&config, &target, &tags, false, yes).await?;
+            }
+        },
+
+        cli::Commands::User { command } => match command {
+            cli::UserCommands::List => {
+                chef::user::list(&config).await?;
+            }
+
+            cli::UserCommands::Show { user_id } => {
+                chef::user::show(&config, &user_id).await?;
+            }
+        },
     }
 
     Ok(())
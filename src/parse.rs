@@ -1,3 +1,4 @@
+use colored::Colorize;
 use serde_json::{Map, Value};
 
 const INDENT: &str = "  ";
@@ -72,3 +73,238 @@ fn traverse_array(arr: &[Value], prefix: &str) {
         }
     }
 }
+
+/// JsonChange - a difference between two JSON documents at a dotted path
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonChange {
+    Added(String, Value),
+    Removed(String, Value),
+    Changed(String, Value, Value),
+}
+
+/// join_path - appends a key to a dotted attribute path
+fn join_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// diff_json - Compares two documents key by key, hashes are walked while arrays and scalars are
+/// compared as a whole.
+pub fn diff_json(before: &Value, after: &Value, prefix: &str) -> Vec<JsonChange> {
+    let mut changes = Vec::new();
+
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                let path = join_path(prefix, key);
+                match b.get(key) {
+                    Some(other) => changes.extend(diff_json(value, other, &path)),
+                    None => changes.push(JsonChange::Removed(path, value.clone())),
+                }
+            }
+            for (key, value) in b {
+                if !a.contains_key(key) {
+                    changes.push(JsonChange::Added(join_path(prefix, key), value.clone()));
+                }
+            }
+        }
+        _ if before != after => changes.push(JsonChange::Changed(
+            prefix.to_string(),
+            before.clone(),
+            after.clone(),
+        )),
+        _ => {}
+    }
+
+    changes
+}
+
+/// display_json_diff - prints removed paths in red, added ones in green and changed ones in
+/// yellow.
+pub fn display_json_diff(changes: &[JsonChange]) {
+    for change in changes {
+        match change {
+            JsonChange::Removed(path, value) => {
+                println!("  {}", format!("- {}: {}", path, value).red())
+            }
+            JsonChange::Added(path, value) => {
+                println!("  {}", format!("+ {}: {}", path, value).green())
+            }
+            JsonChange::Changed(path, old, new) => {
+                println!("  {}", format!("~ {}: {} -> {}", path, old, new).yellow())
+            }
+        }
+    }
+}
+
+/// set_path - sets a dotted path like `nginx.worker.count`, creating missing hashes on the way.
+/// Refuses to replace a value that isn't a hash with one.
+pub fn set_path(root: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    let keys: Vec<&str> = path.split('.').collect();
+    if keys.iter().any(|k| k.is_empty()) {
+        return Err(format!("invalid attribute path: {}", path));
+    }
+
+    let mut current = root;
+    for (i, key) in keys.iter().enumerate() {
+        let map = match current.as_object_mut() {
+            Some(map) => map,
+            None => return Err(format!("{} is not a hash", keys[..i].join("."))),
+        };
+
+        if i == keys.len() - 1 {
+            map.insert(key.to_string(), value);
+            return Ok(());
+        }
+
+        current = map
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+
+    Ok(())
+}
+
+/// delete_path - removes a dotted path, returning the removed value
+pub fn delete_path(root: &mut Value, path: &str) -> Option<Value> {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (parent.split('.').try_fold(root, |v, k| v.get_mut(k))?, key),
+        None => (root, path),
+    };
+
+    parent.as_object_mut()?.remove(key)
+}
+
+/// get_path - looks up a dotted path like `kernel.release`
+pub fn get_path<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(root, |v, k| v.get(k))
+}
+
+/// deep_merge - merges `overlay` into `base` the way Chef merges precedence levels, hashes are
+/// merged key by key while any other value from `overlay` replaces the one in `base`.
+pub fn deep_merge(base: &mut Value, overlay: &Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn set_path_creates_missing_hashes() {
+        let mut doc = json!({"nginx": {"port": 80}});
+        set_path(&mut doc, "nginx.worker.count", json!(4)).unwrap();
+        set_path(&mut doc, "ntp", json!(["a", "b"])).unwrap();
+        assert_eq!(
+            doc,
+            json!({"nginx": {"port": 80, "worker": {"count": 4}}, "ntp": ["a", "b"]})
+        );
+
+        set_path(&mut doc, "nginx.port", json!(8080)).unwrap();
+        assert_eq!(doc["nginx"]["port"], json!(8080));
+    }
+
+    #[test]
+    fn set_path_rejects_bad_paths() {
+        let mut doc = json!({"nginx": {"port": 80}});
+        assert!(set_path(&mut doc, "nginx.port.number", json!(1)).is_err());
+        assert!(set_path(&mut doc, "nginx..port", json!(1)).is_err());
+        assert!(set_path(&mut doc, "", json!(1)).is_err());
+        assert_eq!(doc, json!({"nginx": {"port": 80}}));
+    }
+
+    #[test]
+    fn delete_path_removes_nested_keys() {
+        let mut doc = json!({"nginx": {"port": 80, "user": "www"}, "ntp": true});
+        assert_eq!(delete_path(&mut doc, "nginx.port"), Some(json!(80)));
+        assert_eq!(delete_path(&mut doc, "ntp"), Some(json!(true)));
+        assert_eq!(doc, json!({"nginx": {"user": "www"}}));
+
+        assert_eq!(delete_path(&mut doc, "nginx.missing"), None);
+        assert_eq!(delete_path(&mut doc, "missing.port"), None);
+        assert_eq!(delete_path(&mut doc, "nginx.user.name"), None);
+    }
+
+    #[test]
+    fn deep_merge_merges_hashes_and_replaces_the_rest() {
+        let mut base = json!({
+            "nginx": {"port": 80, "modules": ["gzip"], "user": "www"},
+            "ntp": {"servers": ["a"]}
+        });
+        let overlay = json!({
+            "nginx": {"port": 8080, "modules": ["ssl"], "workers": 4},
+            "ntp": "disabled",
+            "motd": "hello"
+        });
+        deep_merge(&mut base, &overlay);
+        assert_eq!(
+            base,
+            json!({
+                "nginx": {"port": 8080, "modules": ["ssl"], "user": "www", "workers": 4},
+                "ntp": "disabled",
+                "motd": "hello"
+            })
+        );
+    }
+
+    #[test]
+    fn deep_merge_replaces_a_scalar_with_a_hash() {
+        let mut base = json!({"ntp": "disabled"});
+        deep_merge(&mut base, &json!({"ntp": {"servers": ["a"]}}));
+        assert_eq!(base, json!({"ntp": {"servers": ["a"]}}));
+    }
+
+    #[test]
+    fn diff_json_reports_changes_by_path() {
+        let before = json!({
+            "nginx": {"port": 80, "user": "www", "modules": ["gzip"]},
+            "ntp": true
+        });
+        let after = json!({
+            "nginx": {"port": 8080, "modules": ["gzip", "ssl"], "workers": 4},
+            "ntp": true
+        });
+        assert_eq!(
+            diff_json(&before, &after, ""),
+            vec![
+                JsonChange::Changed(
+                    "nginx.modules".to_string(),
+                    json!(["gzip"]),
+                    json!(["gzip", "ssl"])
+                ),
+                JsonChange::Changed("nginx.port".to_string(), json!(80), json!(8080)),
+                JsonChange::Removed("nginx.user".to_string(), json!("www")),
+                JsonChange::Added("nginx.workers".to_string(), json!(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_json_uses_the_prefix_and_compares_types() {
+        assert!(diff_json(&json!({"a": 1}), &json!({"a": 1}), "").is_empty());
+        assert_eq!(
+            diff_json(&json!({"a": {"b": 1}}), &json!({"a": "b"}), "normal"),
+            vec![JsonChange::Changed(
+                "normal.a".to_string(),
+                json!({"b": 1}),
+                json!("b")
+            )]
+        );
+    }
+}
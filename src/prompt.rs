@@ -0,0 +1,32 @@
+use std::io::{self, Write};
+
+/// confirm - asks a yes/no question on the terminal, anything but `y` or `yes` is a no.
+pub fn confirm(question: &str) -> bool {
+    print!("{} (y/N): ", question);
+    if io::stdout().flush().is_err() {
+        return false;
+    }
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// confirm_count - asks to type the number of objects about to be changed, a safeguard for bulk
+/// operations where a stray `y` would be costly.
+pub fn confirm_count(question: &str, count: usize) -> bool {
+    print!("{} Type {} to confirm: ", question, count);
+    if io::stdout().flush().is_err() {
+        return false;
+    }
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    answer.trim() == count.to_string()
+}
@@ -0,0 +1,586 @@
+use serde_json::{Map, Number, Value};
+use thiserror::Error;
+
+/// Errors raised while reading a Ruby DSL file such as `roles/*.rb` or `metadata.rb`
+#[derive(Error, Debug)]
+pub enum RubyDslError {
+    #[error("line {line}: {reason}")]
+    Syntax { line: usize, reason: String },
+}
+
+/// DslCall - a single top level method call from a Ruby DSL file, e.g. `run_list "recipe[base]"`,
+/// with its arguments converted to JSON values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DslCall {
+    pub method: String,
+    pub args: Vec<Value>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Label(String),
+    Str(String),
+    Symbol(String),
+    Number(Number),
+    Words(Vec<String>),
+    Arrow,
+    Comma,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Newline,
+}
+
+/// parse_dsl - Reads a Ruby DSL source made of literal method calls. Only literals are supported:
+/// strings, symbols, numbers, booleans, nil, arrays, hashes and `%w[]` word lists. Anything that
+/// needs a Ruby interpreter (variables, blocks, interpolation) is reported as an error.
+pub fn parse_dsl(source: &str) -> Result<Vec<DslCall>, RubyDslError> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let mut calls = Vec::new();
+
+    loop {
+        parser.skip_newlines();
+        let Some((token, line)) = parser.next() else {
+            break;
+        };
+
+        let method = match token {
+            Token::Ident(m) => m,
+            other => {
+                return Err(RubyDslError::Syntax {
+                    line,
+                    reason: format!("expected a method call, found {:?}", other),
+                })
+            }
+        };
+
+        let args = parser.parse_call_args()?;
+        calls.push(DslCall { method, args, line });
+    }
+
+    Ok(calls)
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, RubyDslError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                tokens.push((Token::Newline, line));
+                line += 1;
+                i += 1;
+            }
+            ' ' | '\t' | '\r' => i += 1,
+            '\\' if chars.get(i + 1) == Some(&'\n') => {
+                // Explicit line continuation
+                line += 1;
+                i += 2;
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            ',' => {
+                tokens.push((Token::Comma, line));
+                i += 1;
+            }
+            '(' => {
+                tokens.push((Token::LParen, line));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::RParen, line));
+                i += 1;
+            }
+            '[' => {
+                tokens.push((Token::LBracket, line));
+                i += 1;
+            }
+            ']' => {
+                tokens.push((Token::RBracket, line));
+                i += 1;
+            }
+            '{' => {
+                tokens.push((Token::LBrace, line));
+                i += 1;
+            }
+            '}' => {
+                tokens.push((Token::RBrace, line));
+                i += 1;
+            }
+            '=' if chars.get(i + 1) == Some(&'>') => {
+                tokens.push((Token::Arrow, line));
+                i += 2;
+            }
+            '\'' | '"' => {
+                let start_line = line;
+                let (s, next) = read_string(&chars, i, &mut line)?;
+                // `"key": value` hash label
+                if chars.get(next) == Some(&':') && chars.get(next + 1) != Some(&':') {
+                    tokens.push((Token::Label(s), start_line));
+                    i = next + 1;
+                } else {
+                    tokens.push((Token::Str(s), start_line));
+                    i = next;
+                }
+            }
+            ':' if chars.get(i + 1) == Some(&'"') || chars.get(i + 1) == Some(&'\'') => {
+                let start_line = line;
+                let (s, next) = read_string(&chars, i + 1, &mut line)?;
+                tokens.push((Token::Symbol(s), start_line));
+                i = next;
+            }
+            ':' if chars.get(i + 1).is_some_and(|n| is_ident_start(*n)) => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && is_ident_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push((Token::Symbol(chars[start..i].iter().collect()), line));
+            }
+            '%' if chars.get(i + 1) == Some(&'w') || chars.get(i + 1) == Some(&'i') => {
+                let open = chars.get(i + 2).copied().unwrap_or(' ');
+                let close = match open {
+                    '[' => ']',
+                    '(' => ')',
+                    '{' => '}',
+                    '<' => '>',
+                    '|' => '|',
+                    _ => {
+                        return Err(RubyDslError::Syntax {
+                            line,
+                            reason: "unsupported %w delimiter".to_string(),
+                        })
+                    }
+                };
+                let start = i + 3;
+                let mut end = start;
+                // Ruby allows nested delimiter pairs, e.g. `%w[recipe[a] role[b]]`
+                let mut depth = 0;
+                while end < chars.len() {
+                    let c = chars[end];
+                    if c == close && depth == 0 {
+                        break;
+                    }
+                    if c == close {
+                        depth -= 1;
+                    } else if c == open && open != close {
+                        depth += 1;
+                    } else if c == '\n' {
+                        line += 1;
+                    }
+                    end += 1;
+                }
+                if end >= chars.len() {
+                    return Err(RubyDslError::Syntax {
+                        line,
+                        reason: "unterminated %w list".to_string(),
+                    });
+                }
+                let words: String = chars[start..end].iter().collect();
+                tokens.push((
+                    Token::Words(words.split_whitespace().map(String::from).collect()),
+                    line,
+                ));
+                i = end + 1;
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                let start = i;
+                i += 1;
+                while i < chars.len()
+                    && (chars[i].is_ascii_digit()
+                        || chars[i] == '_'
+                        || (chars[i] == '.'
+                            && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())))
+                {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+                let number = if literal.contains('.') {
+                    literal.parse::<f64>().ok().and_then(Number::from_f64)
+                } else {
+                    literal.parse::<i64>().ok().map(Number::from)
+                };
+                match number {
+                    Some(n) => tokens.push((Token::Number(n), line)),
+                    None => {
+                        return Err(RubyDslError::Syntax {
+                            line,
+                            reason: format!("invalid number {}", literal),
+                        })
+                    }
+                }
+            }
+            c if is_ident_start(c) => {
+                let start = i;
+                while i < chars.len() && is_ident_char(chars[i]) {
+                    i += 1;
+                }
+                let ident: String = chars[start..i].iter().collect();
+                // `key: value` hash label, but not `key::Const`
+                if chars.get(i) == Some(&':') && chars.get(i + 1) != Some(&':') {
+                    tokens.push((Token::Label(ident), line));
+                    i += 1;
+                } else {
+                    tokens.push((Token::Ident(ident), line));
+                }
+            }
+            _ => {
+                return Err(RubyDslError::Syntax {
+                    line,
+                    reason: format!("unexpected character '{}'", c),
+                })
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '?' || c == '!'
+}
+
+/// read_string - reads a quoted string starting at `start` and returns the value together with
+/// the index after the closing quote.
+fn read_string(
+    chars: &[char],
+    start: usize,
+    line: &mut usize,
+) -> Result<(String, usize), RubyDslError> {
+    let quote = chars[start];
+    let mut value = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == quote {
+            return Ok((value, i + 1));
+        }
+        if c == '\n' {
+            *line += 1;
+        }
+        if c == '\\' && i + 1 < chars.len() {
+            let escaped = chars[i + 1];
+            if quote == '\'' {
+                // Single quoted strings only know \' and \\
+                match escaped {
+                    '\'' | '\\' => value.push(escaped),
+                    _ => {
+                        value.push('\\');
+                        value.push(escaped);
+                    }
+                }
+            } else {
+                match escaped {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    '0' => value.push('\0'),
+                    _ => value.push(escaped),
+                }
+            }
+            i += 2;
+            continue;
+        }
+        if quote == '"' && c == '#' && chars.get(i + 1) == Some(&'{') {
+            return Err(RubyDslError::Syntax {
+                line: *line,
+                reason: "string interpolation is not supported".to_string(),
+            });
+        }
+        value.push(c);
+        i += 1;
+    }
+
+    Err(RubyDslError::Syntax {
+        line: *line,
+        reason: "unterminated string".to_string(),
+    })
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(t, _)| t)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map(|(_, l)| *l)
+            .unwrap_or(1)
+    }
+
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Token::Newline) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, reason: &str) -> RubyDslError {
+        RubyDslError::Syntax {
+            line: self.line(),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), RubyDslError> {
+        match self.next() {
+            Some((t, _)) if t == expected => Ok(()),
+            Some((t, line)) => Err(RubyDslError::Syntax {
+                line,
+                reason: format!("expected {:?}, found {:?}", expected, t),
+            }),
+            None => Err(self.error(&format!("expected {:?}, found end of file", expected))),
+        }
+    }
+
+    /// parse_call_args - reads the arguments of a method call, with or without parentheses.
+    /// Trailing `key => value` pairs are collected into a single hash argument like Ruby does.
+    fn parse_call_args(&mut self) -> Result<Vec<Value>, RubyDslError> {
+        let parenthesized = self.peek() == Some(&Token::LParen);
+        if parenthesized {
+            self.pos += 1;
+            self.skip_newlines();
+            if self.peek() == Some(&Token::RParen) {
+                self.pos += 1;
+                return Ok(Vec::new());
+            }
+        }
+
+        let mut args = Vec::new();
+        let mut pairs = Map::new();
+
+        loop {
+            match self.peek() {
+                None | Some(Token::Newline) if !parenthesized => break,
+                _ => {}
+            }
+
+            if let Some((key, value)) = self.parse_pair()? {
+                pairs.insert(key, value);
+            } else {
+                if !pairs.is_empty() {
+                    return Err(self.error("positional argument after hash arguments"));
+                }
+                args.push(self.parse_value()?);
+            }
+
+            if parenthesized {
+                self.skip_newlines();
+            }
+            match self.peek() {
+                Some(Token::Comma) => {
+                    self.pos += 1;
+                    self.skip_newlines();
+                }
+                Some(Token::RParen) if parenthesized => {
+                    self.pos += 1;
+                    break;
+                }
+                None | Some(Token::Newline) if !parenthesized => break,
+                _ => return Err(self.error("expected ',' or end of arguments")),
+            }
+        }
+
+        if !pairs.is_empty() {
+            args.push(Value::Object(pairs));
+        }
+
+        Ok(args)
+    }
+
+    /// parse_pair - reads `key => value` or `key: value` when the upcoming tokens form a pair
+    fn parse_pair(&mut self) -> Result<Option<(String, Value)>, RubyDslError> {
+        if let Some(Token::Label(key)) = self.peek().cloned() {
+            self.pos += 1;
+            self.skip_newlines();
+            return Ok(Some((key, self.parse_value()?)));
+        }
+
+        let is_pair = matches!(
+            self.tokens.get(self.pos + 1).map(|(t, _)| t),
+            Some(Token::Arrow)
+        ) && matches!(
+            self.peek(),
+            Some(Token::Str(_)) | Some(Token::Symbol(_)) | Some(Token::Number(_))
+        );
+        if !is_pair {
+            return Ok(None);
+        }
+
+        let key = match self.next() {
+            Some((Token::Str(s), _)) | Some((Token::Symbol(s), _)) => s,
+            Some((Token::Number(n), _)) => n.to_string(),
+            _ => unreachable!(),
+        };
+        self.expect(Token::Arrow)?;
+        self.skip_newlines();
+
+        Ok(Some((key, self.parse_value()?)))
+    }
+
+    fn parse_value(&mut self) -> Result<Value, RubyDslError> {
+        match self.next() {
+            Some((Token::Str(s), _)) | Some((Token::Symbol(s), _)) => Ok(Value::String(s)),
+            Some((Token::Number(n), _)) => Ok(Value::Number(n)),
+            Some((Token::Words(w), _)) => {
+                Ok(Value::Array(w.into_iter().map(Value::String).collect()))
+            }
+            Some((Token::Ident(i), line)) => match i.as_str() {
+                "true" => Ok(Value::Bool(true)),
+                "false" => Ok(Value::Bool(false)),
+                "nil" => Ok(Value::Null),
+                _ => Err(RubyDslError::Syntax {
+                    line,
+                    reason: format!("unsupported expression '{}'", i),
+                }),
+            },
+            Some((Token::LBracket, _)) => self.parse_array(),
+            Some((Token::LBrace, _)) => self.parse_hash(),
+            Some((t, line)) => Err(RubyDslError::Syntax {
+                line,
+                reason: format!("unexpected {:?}", t),
+            }),
+            None => Err(self.error("unexpected end of file")),
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, RubyDslError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.peek() == Some(&Token::RBracket) {
+                self.pos += 1;
+                return Ok(Value::Array(items));
+            }
+            items.push(self.parse_value()?);
+            self.skip_newlines();
+            match self.next() {
+                Some((Token::Comma, _)) => {}
+                Some((Token::RBracket, _)) => return Ok(Value::Array(items)),
+                _ => return Err(self.error("expected ',' or ']' in array")),
+            }
+        }
+    }
+
+    fn parse_hash(&mut self) -> Result<Value, RubyDslError> {
+        let mut map = Map::new();
+        loop {
+            self.skip_newlines();
+            if self.peek() == Some(&Token::RBrace) {
+                self.pos += 1;
+                return Ok(Value::Object(map));
+            }
+            match self.parse_pair()? {
+                Some((key, value)) => {
+                    map.insert(key, value);
+                }
+                None => return Err(self.error("expected 'key => value' in hash")),
+            }
+            self.skip_newlines();
+            match self.next() {
+                Some((Token::Comma, _)) => {}
+                Some((Token::RBrace, _)) => return Ok(Value::Object(map)),
+                _ => return Err(self.error("expected ',' or '}' in hash")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(source: &str) -> Vec<Value> {
+        let calls = parse_dsl(source).unwrap();
+        assert_eq!(calls.len(), 1);
+        calls.into_iter().next().unwrap().args
+    }
+
+    #[test]
+    fn parses_literal_calls() {
+        let calls = parse_dsl("name \"web\"\ndescription 'Web servers' # comment\n").unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].method, "name");
+        assert_eq!(calls[0].args, vec![json!("web")]);
+        assert_eq!(calls[1].method, "description");
+        assert_eq!(calls[1].args, vec![json!("Web servers")]);
+        assert_eq!(calls[1].line, 2);
+    }
+
+    #[test]
+    fn word_lists_count_nested_delimiters() {
+        assert_eq!(
+            args("run_list %w[recipe[a] role[b]]"),
+            vec![json!(["recipe[a]", "role[b]"])]
+        );
+        assert_eq!(args("run_list %w(a (b) c)"), vec![json!(["a", "(b)", "c"])]);
+        assert_eq!(args("run_list %w|a b|"), vec![json!(["a", "b"])]);
+    }
+
+    #[test]
+    fn unterminated_word_list_is_an_error() {
+        assert!(parse_dsl("run_list %w[recipe[a]").is_err());
+    }
+
+    #[test]
+    fn hash_keys_in_every_style() {
+        assert_eq!(
+            args("default_attributes \"a\" => 1, :b => true, c: nil, \"d\": [1, 2.5]"),
+            vec![json!({"a": 1, "b": true, "c": null, "d": [1, 2.5]})]
+        );
+        assert_eq!(
+            args("override_attributes({\n  \"x\": { 'y': \"z\" },\n})"),
+            vec![json!({"x": {"y": "z"}})]
+        );
+    }
+
+    #[test]
+    fn positional_then_hash_arguments() {
+        assert_eq!(
+            args("depends 'apt', '>= 1.0', optional: true"),
+            vec![json!("apt"), json!(">= 1.0"), json!({"optional": true})]
+        );
+    }
+
+    #[test]
+    fn unsupported_ruby_is_an_error() {
+        assert!(parse_dsl("name \"#{prefix}-web\"").is_err());
+        assert!(parse_dsl("run_list roles").is_err());
+        assert!(parse_dsl("depends 'apt', optional: true, 'x'").is_err());
+    }
+}